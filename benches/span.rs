@@ -0,0 +1,104 @@
+//! Span kernel throughput at every supported SIMD level, plus a whole frame
+//! shaded at each of them.
+//!
+//! Run with `cargo bench`.
+
+use std::{hint::black_box, time::Instant};
+
+use glam::{IVec2, Vec2, Vec3};
+use rusterizer::{
+    geometry::primitives::Varyings,
+    pixels::{PixelBuffer, PixelFormat},
+    span::{SimdLevel, Span, SpanVaryings, pack_unorm8, shade_span},
+    world::World,
+};
+
+const SPAN_LEN: usize = 640;
+const ROUNDS: usize = 4_000;
+
+/// Best time per round over `batches` batches of `rounds`, less sensitive
+/// to the machine's background load than the mean.
+fn time(name: &str, batches: usize, rounds: usize, mut f: impl FnMut()) -> f64 {
+    f();
+    let per_round = (0..batches)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..rounds {
+                f();
+            }
+            start.elapsed().as_secs_f64() / rounds as f64
+        })
+        .fold(f64::INFINITY, f64::min);
+    println!("{name:<28} {:>10.2} us", per_round * 1e6);
+    per_round
+}
+
+fn main() {
+    let span = Span {
+        point: IVec2::ZERO,
+        len: SPAN_LEN,
+        depth: 0.2,
+        depth_step: 0.5 / SPAN_LEN as f32,
+        z_recip: 0.5,
+        z_recip_step: 0.1 / SPAN_LEN as f32,
+        varyings: Varyings::new(Vec3::new(0.1, 0.2, 0.3), Vec3::ZERO, Vec3::Y, Vec2::ZERO),
+        varyings_step: Varyings::new(
+            Vec3::splat(0.2 / SPAN_LEN as f32),
+            Vec3::splat(1f32 / SPAN_LEN as f32),
+            Vec3::ZERO,
+            Vec2::splat(1f32 / SPAN_LEN as f32),
+        ),
+    };
+    // Every other pixel is occluded.
+    let z_init: Vec<f32> = (0..SPAN_LEN).map(|i| [1f32, 0.4][i % 2]).collect();
+    let channels: Vec<f32> = (0..SPAN_LEN * 3)
+        .map(|i| (i % 300) as f32 / 256f32)
+        .collect();
+
+    let mut baseline = [0f64; 2];
+    for level in SimdLevel::supported() {
+        let mut z_buf = z_init.clone();
+        let mut out = SpanVaryings::default();
+        let mut passed = vec![false; SPAN_LEN];
+        let shade = time(&format!("shade_span {level:?}"), 5, ROUNDS, || {
+            z_buf.copy_from_slice(&z_init);
+            shade_span(level, black_box(&span), &mut z_buf, &mut out, &mut passed);
+            black_box(&out);
+        });
+        let mut packed = vec![0u8; SPAN_LEN * 3];
+        let pack = time(&format!("pack_unorm8 {level:?}"), 5, ROUNDS, || {
+            pack_unorm8(level, black_box(&channels), &mut packed);
+            black_box(&packed);
+        });
+        if level == SimdLevel::Scalar {
+            baseline = [shade, pack];
+        } else {
+            println!(
+                "  speedup over scalar: shade {:.2}x, pack {:.2}x",
+                baseline[0] / shade,
+                baseline[1] / pack
+            );
+        }
+    }
+
+    let (height, width) = (720, 1280);
+    let mut world = World::new(height, width, PixelFormat::Rgba8);
+    let pitch = width as usize * PixelFormat::Rgba8.bytes_per_pixel();
+    let mut buf = vec![0u8; pitch * height as usize];
+    // Only shading follows the level, quantizing always uses the widest.
+    let mut baseline = 0f64;
+    for level in SimdLevel::supported() {
+        world.set_simd_level(level).unwrap();
+        let frame = time(&format!("frame {width}x{height} {level:?}"), 30, 1, || {
+            let writer =
+                PixelBuffer::new(width, height, pitch, PixelFormat::Rgba8, &mut buf).unwrap();
+            world.draw(writer);
+            black_box(&buf);
+        });
+        if level == SimdLevel::Scalar {
+            baseline = frame;
+        } else {
+            println!("  speedup over scalar: frame {:.2}x", baseline / frame);
+        }
+    }
+}
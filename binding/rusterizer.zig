@@ -1,4 +1,173 @@
-pub extern fn rusterizer_init_scene(h: u32, w: u32) bool;
-pub extern fn rusterizer_deinit_world() bool;
-pub extern fn rusterizer_draw_to_pixel_buf(buf: [*c]u8) bool;
-pub extern fn rusterizer_camera_yaw(yaw: f32) void;
+pub const World = opaque {};
+
+pub const Status = enum(c_int) {
+    ok = 0,
+    null_handle = 1,
+    null_pointer = 2,
+    lock_poisoned = 3,
+    invalid_argument = 4,
+    panic = 5,
+    io = 6,
+    _,
+};
+
+pub const PixelFormat = enum(u32) {
+    rgba8 = 0,
+    bgra8 = 1,
+    rgb8 = 2,
+    rgb565 = 3,
+    gray8 = 4,
+    rgba32f = 5,
+};
+
+pub const CullMode = enum(u32) {
+    none = 0,
+    back = 1,
+    front = 2,
+};
+
+/// On-screen vertex order, with y pointing down.
+pub const Winding = enum(u32) {
+    clockwise = 0,
+    counter_clockwise = 1,
+};
+
+pub const InterpolationMode = enum(u32) {
+    perspective = 0,
+    affine = 1,
+};
+
+pub const ShadingMode = enum(u32) {
+    gouraud = 0,
+    phong = 1,
+};
+
+pub const Rasterizer = enum(u32) {
+    scanline = 0,
+    edge_function = 1,
+};
+
+pub const AddressMode = enum(u32) {
+    repeat = 0,
+    clamp_to_edge = 1,
+    mirrored_repeat = 2,
+};
+
+pub const TextureFilter = enum(u32) {
+    nearest = 0,
+    bilinear = 1,
+    trilinear = 2,
+    anisotropic = 3,
+};
+
+/// Interpolated attributes of a fragment, see `FragmentCallback`.
+pub const Fragment = extern struct {
+    /// Canvas pixel, of the supersampled canvas with supersampling.
+    x: i32,
+    y: i32,
+    mesh: u32,
+    position: [3]f32,
+    /// Unit normal, zero if degenerate.
+    normal: [3]f32,
+    uv: [2]f32,
+};
+
+/// Overwrites the linear RGB `color` of a fragment in place. Called
+/// concurrently from the render threads while the world is locked, so it
+/// must not call back into the library with the same world.
+pub const FragmentCallback = *const fn (user_data: ?*anyopaque, fragment: *const Fragment, color: *[3]f32) callconv(.c) void;
+
+pub const DownsampleFilter = enum(u32) {
+    box = 0,
+    tent = 1,
+    lanczos3 = 2,
+};
+
+pub extern fn rusterizer_last_error_message() ?[*:0]const u8;
+
+pub extern fn rusterizer_world_create(h: u32, w: u32, format: PixelFormat, out: *?*World) Status;
+pub extern fn rusterizer_world_destroy(world: ?*World) Status;
+pub extern fn rusterizer_draw_to_pixel_buf(world: ?*const World, buf: [*c]u8, pitch: usize) Status;
+pub extern fn rusterizer_camera_yaw(world: ?*const World, yaw: f32) Status;
+pub extern fn rusterizer_camera_set_orientation(world: ?*const World, yaw: f32, pitch: f32, roll: f32) Status;
+pub extern fn rusterizer_camera_rotate(world: ?*const World, yaw: f32, pitch: f32, roll: f32) Status;
+pub extern fn rusterizer_camera_set_position(world: ?*const World, x: f32, y: f32, z: f32) Status;
+pub extern fn rusterizer_camera_translate(world: ?*const World, x: f32, y: f32, z: f32) Status;
+pub extern fn rusterizer_camera_move_local(world: ?*const World, right: f32, down: f32, forward: f32) Status;
+pub extern fn rusterizer_camera_look_at(
+    world: ?*const World,
+    eye_x: f32,
+    eye_y: f32,
+    eye_z: f32,
+    target_x: f32,
+    target_y: f32,
+    target_z: f32,
+    up_x: f32,
+    up_y: f32,
+    up_z: f32,
+) Status;
+pub extern fn rusterizer_camera_set_perspective(world: ?*const World, fov_y: f32, near: f32, far: f32) Status;
+pub extern fn rusterizer_camera_set_orthographic(world: ?*const World, height: f32, near: f32, far: f32) Status;
+pub extern fn rusterizer_camera_set_aspect(world: ?*const World, aspect: f32) Status;
+pub extern fn rusterizer_set_cull_mode(world: ?*const World, mode: CullMode) Status;
+pub extern fn rusterizer_set_front_face(world: ?*const World, winding: Winding) Status;
+pub extern fn rusterizer_mesh_count(world: ?*const World, out: *u32) Status;
+pub extern fn rusterizer_mesh_set_cull_mode(world: ?*const World, mesh: u32, mode: CullMode) Status;
+pub extern fn rusterizer_mesh_reset_cull_mode(world: ?*const World, mesh: u32) Status;
+pub extern fn rusterizer_set_interpolation_mode(world: ?*const World, mode: InterpolationMode) Status;
+/// Worlds start with Gouraud shading, which draws no shadows: select Phong
+/// shading to see them.
+pub extern fn rusterizer_set_shading_mode(world: ?*const World, mode: ShadingMode) Status;
+/// `map_size` of 0 disables shadows, which are only drawn with Phong shading.
+/// Worlds start without shadows.
+pub extern fn rusterizer_set_shadows(world: ?*const World, map_size: u32, pcf_radius: u32) Status;
+pub extern fn rusterizer_set_rasterizer(world: ?*const World, rasterizer: Rasterizer) Status;
+/// `samples` is 1, 2, 4 or 8, 1 (or 0) disables multisampling.
+pub extern fn rusterizer_set_msaa(world: ?*const World, samples: u32) Status;
+/// `factor` of 1 disables supersampling.
+pub extern fn rusterizer_set_supersampling(world: ?*const World, factor: u32, filter: DownsampleFilter) Status;
+/// `threads` of 0 uses one thread per core, 1 renders on the calling thread.
+pub extern fn rusterizer_set_thread_count(world: ?*const World, threads: u32) Status;
+pub extern fn rusterizer_light_count(world: ?*const World, out: *u32) Status;
+/// `out`, when not null, receives the index of the new light.
+pub extern fn rusterizer_light_add_point(world: ?*const World, x: f32, y: f32, z: f32, r: f32, g: f32, b: f32, out: ?*u32) Status;
+pub extern fn rusterizer_light_add_directional(world: ?*const World, dx: f32, dy: f32, dz: f32, r: f32, g: f32, b: f32, out: ?*u32) Status;
+pub extern fn rusterizer_light_add_spot(
+    world: ?*const World,
+    x: f32,
+    y: f32,
+    z: f32,
+    dx: f32,
+    dy: f32,
+    dz: f32,
+    r: f32,
+    g: f32,
+    b: f32,
+    inner: f32,
+    outer: f32,
+    out: ?*u32,
+) Status;
+pub extern fn rusterizer_light_remove(world: ?*const World, light: u32) Status;
+pub extern fn rusterizer_light_set_enabled(world: ?*const World, light: u32, enabled: bool) Status;
+pub extern fn rusterizer_light_set_position(world: ?*const World, light: u32, x: f32, y: f32, z: f32) Status;
+pub extern fn rusterizer_light_translate(world: ?*const World, light: u32, x: f32, y: f32, z: f32) Status;
+pub extern fn rusterizer_light_set_direction(world: ?*const World, light: u32, x: f32, y: f32, z: f32) Status;
+pub extern fn rusterizer_light_set_power(world: ?*const World, light: u32, r: f32, g: f32, b: f32) Status;
+/// Cone half angles in radians.
+pub extern fn rusterizer_light_set_cone(world: ?*const World, light: u32, inner: f32, outer: f32) Status;
+pub extern fn rusterizer_set_ambient_light(world: ?*const World, r: f32, g: f32, b: f32) Status;
+/// `out`, when not null, receives the index of the new texture.
+pub extern fn rusterizer_texture_create_rgba8(world: ?*const World, width: u32, height: u32, data: [*c]const u8, pitch: usize, out: ?*u32) Status;
+/// Loads a PPM or TGA image.
+pub extern fn rusterizer_texture_load(world: ?*const World, path: [*:0]const u8, out: ?*u32) Status;
+pub extern fn rusterizer_texture_set_address_mode(world: ?*const World, texture: u32, mode: AddressMode) Status;
+pub extern fn rusterizer_texture_set_filter(world: ?*const World, texture: u32, filter: TextureFilter) Status;
+pub extern fn rusterizer_mesh_set_texture(world: ?*const World, mesh: u32, texture: u32) Status;
+pub extern fn rusterizer_mesh_reset_texture(world: ?*const World, mesh: u32) Status;
+/// A null callback removes the current one. `user_data` must outlive it.
+pub extern fn rusterizer_set_fragment_callback(world: ?*const World, callback: ?FragmentCallback, user_data: ?*anyopaque) Status;
+/// Adds one mesh per group of a Wavefront OBJ file, `out`, when not null,
+/// receiving the index of the first one.
+pub extern fn rusterizer_load_obj(world: ?*const World, path: [*:0]const u8, out: ?*u32) Status;
+/// Removes every mesh, the Cornell box included.
+pub extern fn rusterizer_mesh_clear(world: ?*const World) Status;
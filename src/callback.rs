@@ -0,0 +1,132 @@
+//! Fragment callbacks letting C code recolor what the built-in shading
+//! draws.
+//!
+//! # Threading
+//!
+//! With more than one render thread the callback is called concurrently
+//! from the render threads, otherwise on the thread drawing the world.
+//! The world lock is held meanwhile, so the callback must not call back
+//! into the library with the same world handle. It is called for every
+//! fragment passing the depth test, in no particular order, including
+//! fragments later hidden by closer ones, and with MSAA once per covered
+//! pixel and polygon. Shadow passes and [`World::draw_with`] never call it.
+//!
+//! [`World::draw_with`]: crate::world::World::draw_with
+
+use std::ffi::c_void;
+
+use glam::{IVec2, Vec3};
+
+use crate::geometry::primitives::Varyings;
+
+/// Interpolated attributes of a fragment, handed to a
+/// [`RusterizerFragmentFn`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RusterizerFragment {
+    /// Canvas pixel, from the top-left corner. With supersampling, pixel
+    /// of the supersampled canvas.
+    pub x: i32,
+    pub y: i32,
+    /// Index of the mesh the fragment belongs to.
+    pub mesh: u32,
+    /// World space position.
+    pub position: [f32; 3],
+    /// Unit world space normal, zero if degenerate.
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+/// Called with `user_data`, the fragment, and its linear RGB color,
+/// holding the color the world computed and to be overwritten in place.
+pub type RusterizerFragmentFn = unsafe extern "C" fn(
+    user_data: *mut c_void,
+    fragment: *const RusterizerFragment,
+    color: *mut f32,
+);
+
+/// A callback and the user data it is called with.
+#[derive(Debug, Clone, Copy)]
+pub struct FragmentCallback {
+    callback: RusterizerFragmentFn,
+    user_data: *mut c_void,
+}
+
+// SAFETY: callers of `FragmentCallback::new` promise the callback may be
+// called from any thread, concurrently, with `user_data`.
+unsafe impl Send for FragmentCallback {}
+unsafe impl Sync for FragmentCallback {}
+
+impl FragmentCallback {
+    /// # Safety
+    ///
+    /// `callback` must be safe to call with `user_data` from any thread,
+    /// concurrently, as described in the [module docs](self), for as
+    /// long as the callback is installed in a world.
+    pub unsafe fn new(callback: RusterizerFragmentFn, user_data: *mut c_void) -> Self {
+        Self {
+            callback,
+            user_data,
+        }
+    }
+
+    /// Color of a fragment of `mesh` at `point`, starting from `color`.
+    pub fn call(&self, point: IVec2, mesh: usize, varyings: &Varyings, color: Vec3) -> Vec3 {
+        let fragment = RusterizerFragment {
+            x: point.x,
+            y: point.y,
+            mesh: mesh as u32,
+            position: varyings.position.to_array(),
+            normal: varyings.normal.normalize_or_zero().to_array(),
+            uv: varyings.uv.to_array(),
+        };
+        let mut color = color.to_array();
+        // SAFETY: guaranteed by the caller of `FragmentCallback::new`.
+        unsafe { (self.callback)(self.user_data, &fragment, color.as_mut_ptr()) };
+        Vec3::from_array(color)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        ffi::c_void,
+        sync::atomic::{AtomicU32, Ordering},
+    };
+
+    use super::{FragmentCallback, RusterizerFragment};
+    use crate::{
+        pixels::PixelFormat,
+        world::{World, draw_rgba32f},
+    };
+
+    /// Counts its calls in `user_data` and paints everything red.
+    unsafe extern "C" fn tint_red(
+        user_data: *mut c_void,
+        fragment: *const RusterizerFragment,
+        color: *mut f32,
+    ) {
+        let calls = unsafe { &*(user_data as *const AtomicU32) };
+        // Panicking across the C ABI aborts, count out of range meshes.
+        let valid = unsafe { (*fragment).mesh } < 3;
+        calls.fetch_add(if valid { 1 } else { 1 << 24 }, Ordering::Relaxed);
+        let color = unsafe { std::slice::from_raw_parts_mut(color, 3) };
+        color.copy_from_slice(&[1f32, 0f32, 0f32]);
+    }
+
+    #[test]
+    fn callback_recolors_every_fragment() {
+        let calls = AtomicU32::new(0);
+        let mut world = World::new(100, 150, PixelFormat::Rgba32F);
+        world.set_thread_count(3).unwrap();
+        let callback = unsafe { FragmentCallback::new(tint_red, &calls as *const _ as *mut _) };
+        world.set_fragment_callback(Some(callback));
+
+        let floats = draw_rgba32f(&world);
+
+        let calls = calls.load(Ordering::Relaxed);
+        assert!(calls > 0 && calls < 1 << 24, "{calls} calls");
+        assert!(floats.chunks_exact(4).any(|p| p[0] == 1f32));
+        assert!(floats.chunks_exact(4).all(|p| p[1] == 0f32 && p[2] == 0f32));
+    }
+}
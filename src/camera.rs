@@ -1,12 +1,33 @@
-use glam::{Mat3, Vec3};
+use std::f32::consts::PI;
 
-use crate::shaders::VertexShaderImpl;
+use glam::{Mat3, Mat4, Vec2, Vec3};
 
+use crate::{
+    error::{Error, Result},
+    shaders::VertexShaderImpl,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// Pinhole projection with a vertical field of view in radians.
+    Perspective { fov_y: f32 },
+    /// Parallel projection of a view volume `height` world units tall.
+    Orthographic { height: f32 },
+}
+
+/// Camera looking down its local +z axis, with +x to the right
+/// and +y pointing down the screen.
+#[derive(Clone)]
 pub struct Camera {
     pub width: u32,
     pub height: u32,
-    pub focal: u32,
+    pub projection: Projection,
+    /// Width over height of the view volume.
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32,
     pub position: Vec3,
+    /// World to camera space rotation.
     pub rotation: Mat3,
 }
 
@@ -15,17 +36,252 @@ impl Camera {
         Self {
             width,
             height,
-            focal: width,
-            position: Vec3::new(0f32, 0f32, -3.001),
+            // Same framing as a focal length of `width` pixels.
+            projection: Projection::Perspective {
+                fov_y: 2f32 * (0.5 * height as f32 / width as f32).atan(),
+            },
+            aspect: width as f32 / height as f32,
+            near: 0.1,
+            far: 100f32,
+            position: Vec3::new(0f32, 0f32, -3f32),
             rotation: Mat3::IDENTITY,
         }
     }
 
+    pub fn look_at(height: u32, width: u32, eye: Vec3, target: Vec3, up: Vec3) -> Result<Self> {
+        let mut camera = Self::new(height, width);
+        camera.set_look_at(eye, target, up)?;
+        Ok(camera)
+    }
+
     pub fn set_yaw(&mut self, yaw: f32) {
         self.rotation = Mat3::from_rotation_y(yaw);
     }
 
+    /// Replaces the orientation, applying yaw first, then pitch, then roll.
+    pub fn set_orientation(&mut self, yaw: f32, pitch: f32, roll: f32) {
+        self.rotation =
+            Mat3::from_rotation_z(roll) * Mat3::from_rotation_x(pitch) * Mat3::from_rotation_y(yaw);
+    }
+
+    /// Turns around the camera's own vertical axis.
+    pub fn yaw(&mut self, angle: f32) {
+        self.rotation = Mat3::from_rotation_y(angle) * self.rotation;
+    }
+
+    /// Tilts around the camera's own horizontal axis.
+    pub fn pitch(&mut self, angle: f32) {
+        self.rotation = Mat3::from_rotation_x(angle) * self.rotation;
+    }
+
+    /// Rolls around the viewing direction.
+    pub fn roll(&mut self, angle: f32) {
+        self.rotation = Mat3::from_rotation_z(angle) * self.rotation;
+    }
+
+    pub fn set_position(&mut self, position: Vec3) {
+        self.position = position;
+    }
+
+    /// Moves the camera by `offset` expressed in world space.
+    pub fn translate(&mut self, offset: Vec3) {
+        self.position += offset;
+    }
+
+    /// Moves the camera by `offset` expressed in camera space
+    /// (right, down, forward).
+    pub fn move_local(&mut self, offset: Vec3) {
+        self.position += self.rotation.transpose() * offset;
+    }
+
+    pub fn set_look_at(&mut self, eye: Vec3, target: Vec3, up: Vec3) -> Result<()> {
+        let forward = (target - eye).try_normalize().ok_or_else(|| {
+            Error::invalid_argument("look-at target must differ from the eye position")
+        })?;
+        let right = forward.cross(up).try_normalize().ok_or_else(|| {
+            Error::invalid_argument("look-at up vector must not be parallel to the view direction")
+        })?;
+        let down = forward.cross(right);
+
+        self.position = eye;
+        self.rotation = Mat3::from_cols(right, down, forward).transpose();
+        Ok(())
+    }
+
+    pub fn set_perspective(&mut self, fov_y: f32, near: f32, far: f32) -> Result<()> {
+        if !(fov_y > 0f32 && fov_y < PI) {
+            return Err(Error::invalid_argument(format!(
+                "field of view must be within (0, pi), got {fov_y}"
+            )));
+        }
+        if !(near > 0f32 && far.is_finite() && far > near) {
+            return Err(Error::invalid_argument(format!(
+                "clip planes must be finite and satisfy 0 < near < far, got near {near} and far {far}"
+            )));
+        }
+        self.projection = Projection::Perspective { fov_y };
+        self.near = near;
+        self.far = far;
+        Ok(())
+    }
+
+    pub fn set_orthographic(&mut self, height: f32, near: f32, far: f32) -> Result<()> {
+        if !(height > 0f32 && height.is_finite()) {
+            return Err(Error::invalid_argument(format!(
+                "orthographic view height must be positive, got {height}"
+            )));
+        }
+        if !(near.is_finite() && far.is_finite() && far > near) {
+            return Err(Error::invalid_argument(format!(
+                "clip planes must satisfy near < far, got near {near} and far {far}"
+            )));
+        }
+        self.projection = Projection::Orthographic { height };
+        self.near = near;
+        self.far = far;
+        Ok(())
+    }
+
+    pub fn set_aspect(&mut self, aspect: f32) -> Result<()> {
+        if !(aspect > 0f32 && aspect.is_finite()) {
+            return Err(Error::invalid_argument(format!(
+                "aspect ratio must be positive, got {aspect}"
+            )));
+        }
+        self.aspect = aspect;
+        Ok(())
+    }
+
+    /// Same view rendered on a canvas `factor` times larger on each axis.
+    pub fn scaled(&self, factor: u32) -> Camera {
+        Camera {
+            width: self.width * factor,
+            height: self.height * factor,
+            ..self.clone()
+        }
+    }
+
+    pub fn viewport(&self) -> Vec2 {
+        Vec2::new(self.width as f32, self.height as f32)
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::from_mat3(self.rotation) * Mat4::from_translation(-self.position)
+    }
+
+    /// Maps camera space to clip space, with depth in `[0, 1]`
+    /// between the near and far planes.
+    pub fn projection_matrix(&self) -> Mat4 {
+        match self.projection {
+            Projection::Perspective { fov_y } => {
+                Mat4::perspective_lh(fov_y, self.aspect, self.near, self.far)
+            }
+            Projection::Orthographic { height } => {
+                let half_height = height / 2f32;
+                let half_width = half_height * self.aspect;
+                Mat4::orthographic_lh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    self.near,
+                    self.far,
+                )
+            }
+        }
+    }
+
+    pub fn view_projection_matrix(&self) -> Mat4 {
+        self.projection_matrix() * self.view_matrix()
+    }
+
     pub fn as_vertex_shader(&self) -> VertexShaderImpl {
         VertexShaderImpl::wrap_camera(self)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::f32::consts::PI;
+
+    use glam::{Vec3, vec3};
+
+    use super::{Camera, Projection};
+
+    #[test]
+    fn look_at_matches_default_orientation() {
+        let camera = Camera::look_at(
+            10,
+            10,
+            vec3(0f32, 0f32, -3f32),
+            Vec3::ZERO,
+            vec3(0f32, -1f32, 0f32),
+        )
+        .unwrap();
+        assert!(
+            camera
+                .rotation
+                .abs_diff_eq(Camera::new(10, 10).rotation, 1e-6)
+        );
+
+        let mut camera = camera;
+        camera.yaw(std::f32::consts::FRAC_PI_2);
+        camera.move_local(vec3(0f32, 0f32, 1f32));
+        assert!(camera.position.abs_diff_eq(vec3(-1f32, 0f32, -3f32), 1e-6));
+    }
+
+    #[test]
+    fn perspective_rejects_bad_arguments() {
+        let mut camera = Camera::new(10, 10);
+        let before = camera.projection;
+        for (fov_y, near, far) in [
+            (1f32, 0.1, f32::INFINITY),
+            (1f32, 0.1, f32::NAN),
+            (1f32, 2f32, 2f32),
+            (1f32, 3f32, 2f32),
+            (1f32, 0f32, 2f32),
+            (0f32, 0.1, 100f32),
+            (-1f32, 0.1, 100f32),
+            (PI, 0.1, 100f32),
+            (f32::NAN, 0.1, 100f32),
+        ] {
+            assert!(
+                camera.set_perspective(fov_y, near, far).is_err(),
+                "fov {fov_y}, near {near}, far {far}"
+            );
+        }
+        // Rejected settings leave the camera untouched.
+        assert_eq!(before, camera.projection);
+        assert_eq!((0.1, 100f32), (camera.near, camera.far));
+
+        camera.set_perspective(1f32, 0.5, 50f32).unwrap();
+        assert_eq!(Projection::Perspective { fov_y: 1f32 }, camera.projection);
+        assert_eq!((0.5, 50f32), (camera.near, camera.far));
+    }
+
+    #[test]
+    fn orthographic_rejects_bad_arguments() {
+        let mut camera = Camera::new(10, 10);
+        for (height, near, far) in [
+            (0f32, 0.1, 100f32),
+            (-1f32, 0.1, 100f32),
+            (f32::INFINITY, 0.1, 100f32),
+            (f32::NAN, 0.1, 100f32),
+            (1f32, 0.1, f32::INFINITY),
+            (1f32, f32::NEG_INFINITY, 100f32),
+            (1f32, 2f32, 2f32),
+            (1f32, 3f32, 2f32),
+        ] {
+            assert!(
+                camera.set_orthographic(height, near, far).is_err(),
+                "height {height}, near {near}, far {far}"
+            );
+        }
+        assert!(matches!(camera.projection, Projection::Perspective { .. }));
+
+        // Unlike a perspective one, the view volume may start behind the eye.
+        camera.set_orthographic(4f32, -1f32, 10f32).unwrap();
+        assert_eq!(Projection::Orthographic { height: 4f32 }, camera.projection);
+        assert_eq!((-1f32, 10f32), (camera.near, camera.far));
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    cell::RefCell,
+    ffi::{CString, c_char},
+    io,
+    panic::{AssertUnwindSafe, catch_unwind},
+    path::Path,
+    ptr::null,
+};
+
+/// Status code returned by every exported function.
+///
+/// The discriminants are part of the C ABI and must never be reordered.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RusterizerStatus {
+    Ok = 0,
+    NullHandle = 1,
+    NullPointer = 2,
+    LockPoisoned = 3,
+    InvalidArgument = 4,
+    Panic = 5,
+    /// A file could not be read.
+    Io = 6,
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub status: RusterizerStatus,
+    pub message: String,
+}
+
+impl Error {
+    pub fn new(status: RusterizerStatus, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::new(RusterizerStatus::InvalidArgument, message)
+    }
+
+    pub fn io(path: &Path, err: io::Error) -> Self {
+        Self::new(RusterizerStatus::Io, format!("{}: {err}", path.display()))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: &str) {
+    // Interior NULs would truncate the message on the C side anyway.
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with_borrow_mut(|last| last.replace(message));
+}
+
+/// Runs `f` at the FFI boundary, turning its result (or a panic)
+/// into a status code and recording the message for the calling thread.
+pub(crate) fn ffi_call(f: impl FnOnce() -> Result<()>) -> RusterizerStatus {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => RusterizerStatus::Ok,
+        Ok(Err(err)) => {
+            set_last_error(&err.message);
+            err.status
+        }
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown panic");
+            set_last_error(&format!("panicked: {message}"));
+            RusterizerStatus::Panic
+        }
+    }
+}
+
+/// Returns the message of the last failed call made on the current thread,
+/// or null if no call has failed yet.
+///
+/// The pointer stays valid until the next failing call on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn rusterizer_last_error_message() -> *const c_char {
+    LAST_ERROR.with_borrow(|last| last.as_ref().map_or(null(), |message| message.as_ptr()))
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::CStr;
+
+    use super::{Error, RusterizerStatus, ffi_call, rusterizer_last_error_message};
+
+    fn last_error() -> String {
+        let message = rusterizer_last_error_message();
+        unsafe { CStr::from_ptr(message) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn ffi_call_records_last_error() {
+        let status = ffi_call(|| Err(Error::invalid_argument("bad width")));
+        assert_eq!(RusterizerStatus::InvalidArgument, status);
+        assert_eq!("bad width", last_error());
+
+        let status = ffi_call(|| panic!("boom"));
+        assert_eq!(RusterizerStatus::Panic, status);
+        assert_eq!("panicked: boom", last_error());
+
+        assert_eq!(RusterizerStatus::Ok, ffi_call(|| Ok(())));
+    }
+}
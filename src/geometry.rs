@@ -1 +1,4 @@
+pub mod clipping;
+pub mod culling;
+pub mod mesh;
 pub mod primitives;
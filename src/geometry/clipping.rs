@@ -0,0 +1,105 @@
+use glam::Vec4;
+
+use crate::{geometry::primitives::ClipVertex, operations::Interpolant};
+
+/// Near plane in clip space, depth is `[0, w]` in front of it.
+pub const NEAR_PLANE: Vec4 = Vec4::new(0f32, 0f32, 1f32, 0f32);
+
+/// How far past the viewport edges, in viewport half-extents, polygons
+/// may reach before being clipped. Rasterization clamps to the canvas
+/// anyway; this only bounds screen coordinates and edge walking work.
+pub const GUARD_BAND: f32 = 4f32;
+
+const GUARD_BAND_PLANES: [Vec4; 4] = [
+    Vec4::new(1f32, 0f32, 0f32, GUARD_BAND),
+    Vec4::new(-1f32, 0f32, 0f32, GUARD_BAND),
+    Vec4::new(0f32, 1f32, 0f32, GUARD_BAND),
+    Vec4::new(0f32, -1f32, 0f32, GUARD_BAND),
+];
+
+/// Sutherland–Hodgman pass keeping the part of a convex polygon
+/// where `plane.dot(position) >= 0`.
+pub fn clip_polygon<V: Interpolant>(vertices: &[ClipVertex<V>], plane: Vec4) -> Vec<ClipVertex<V>> {
+    let mut clipped = Vec::with_capacity(vertices.len() + 1);
+    let edges = vertices.iter().zip(vertices.iter().cycle().skip(1));
+    for (current, next) in edges {
+        let current_distance = plane.dot(current.position);
+        let next_distance = plane.dot(next.position);
+        if current_distance >= 0f32 {
+            clipped.push(*current);
+        }
+        if (current_distance >= 0f32) != (next_distance >= 0f32) {
+            let t = current_distance / (current_distance - next_distance);
+            clipped.push(current.lerp(next, t));
+        }
+    }
+    clipped
+}
+
+pub fn clip_near<V: Interpolant>(vertices: &[ClipVertex<V>]) -> Vec<ClipVertex<V>> {
+    clip_polygon(vertices, NEAR_PLANE)
+}
+
+/// Clips against the near plane, then against the guard band.
+pub fn clip_to_guard_band<V: Interpolant>(vertices: &[ClipVertex<V>]) -> Vec<ClipVertex<V>> {
+    GUARD_BAND_PLANES
+        .iter()
+        .fold(clip_near(vertices), |clipped, plane| {
+            if clipped.is_empty() {
+                return clipped;
+            }
+            clip_polygon(&clipped, *plane)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use glam::{Vec2, Vec3, vec4};
+
+    use super::{GUARD_BAND, clip_near, clip_to_guard_band};
+    use crate::geometry::primitives::{ClipVertex, Varyings};
+
+    fn vertex(position: glam::Vec4, color: Vec3) -> ClipVertex {
+        ClipVertex::new(
+            position,
+            Varyings::new(color, Vec3::ZERO, Vec3::ZERO, Vec2::ZERO),
+        )
+    }
+
+    #[test]
+    fn clip_triangle_crossing_near_plane() {
+        let triangle = [
+            vertex(vec4(0f32, 0f32, 1f32, 2f32), Vec3::ONE),
+            vertex(vec4(1f32, 0f32, -1f32, 0.5), Vec3::ZERO),
+            vertex(vec4(0f32, 1f32, 1f32, 2f32), Vec3::ONE),
+        ];
+        let clipped = clip_near(&triangle);
+        assert_eq!(4, clipped.len());
+        assert!(clipped.iter().all(|v| v.position.z >= 0f32));
+        assert_eq!(vec4(0.5, 0f32, 0f32, 1.25), clipped[1].position);
+        assert_eq!(Vec3::splat(0.5), clipped[1].varyings.color);
+
+        let behind = triangle.map(|mut v| {
+            v.position.z = -1f32;
+            v
+        });
+        assert!(clip_near(&behind).is_empty());
+    }
+
+    #[test]
+    fn clip_to_guard_band_bounds_ndc() {
+        let huge = [
+            vertex(vec4(-100f32, 0f32, 0.5, 1f32), Vec3::ZERO),
+            vertex(vec4(100f32, -100f32, 0.5, 1f32), Vec3::ZERO),
+            vertex(vec4(100f32, 100f32, 0.5, 1f32), Vec3::ZERO),
+        ];
+        let clipped = clip_to_guard_band(&huge);
+        assert!(!clipped.is_empty());
+        let limit = GUARD_BAND + 1e-4;
+        assert!(
+            clipped
+                .iter()
+                .all(|v| v.position.x.abs() <= limit && v.position.y.abs() <= limit)
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{
+    error::{Error, Result},
+    geometry::primitives::Polygon2D,
+};
+
+/// Which faces get discarded before rasterization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullMode {
+    None,
+    Back,
+    Front,
+}
+
+impl TryFrom<u32> for CullMode {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self> {
+        Ok(match value {
+            0 => CullMode::None,
+            1 => CullMode::Back,
+            2 => CullMode::Front,
+            _ => {
+                return Err(Error::invalid_argument(format!(
+                    "unknown cull mode {value}"
+                )));
+            }
+        })
+    }
+}
+
+/// Vertex order of a polygon as seen on screen, with y pointing down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Winding {
+    Clockwise,
+    CounterClockwise,
+}
+
+impl TryFrom<u32> for Winding {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self> {
+        Ok(match value {
+            0 => Winding::Clockwise,
+            1 => Winding::CounterClockwise,
+            _ => return Err(Error::invalid_argument(format!("unknown winding {value}"))),
+        })
+    }
+}
+
+impl Winding {
+    /// `None` for degenerate polygons covering no area.
+    pub fn of<V>(polygon: &Polygon2D<V>) -> Option<Winding> {
+        let vertices = &polygon.vertices;
+        let doubled_area: i64 = vertices
+            .iter()
+            .zip(vertices.iter().cycle().skip(1))
+            .map(|(a, b)| {
+                let (a, b) = (a.point.as_i64vec2(), b.point.as_i64vec2());
+                a.x * b.y - b.x * a.y
+            })
+            .sum();
+        match doubled_area.signum() {
+            1 => Some(Winding::Clockwise),
+            -1 => Some(Winding::CounterClockwise),
+            _ => None,
+        }
+    }
+}
+
+impl CullMode {
+    pub fn culls<V>(self, polygon: &Polygon2D<V>, front_face: Winding) -> bool {
+        let Some(winding) = Winding::of(polygon) else {
+            return self != CullMode::None;
+        };
+        match self {
+            CullMode::None => false,
+            CullMode::Back => winding != front_face,
+            CullMode::Front => winding == front_face,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glam::ivec2;
+
+    use super::{CullMode, Winding};
+    use crate::geometry::primitives::Polygon2D;
+
+    #[test]
+    fn cull_by_screen_winding() {
+        // Top-left, top-right, bottom-left: clockwise with y pointing down.
+        let clockwise = Polygon2D::from_points(&[ivec2(0, 0), ivec2(4, 0), ivec2(0, 4)]);
+        let counter_clockwise = Polygon2D::from_points(&[ivec2(0, 0), ivec2(0, 4), ivec2(4, 0)]);
+        assert_eq!(Some(Winding::Clockwise), Winding::of(&clockwise));
+        assert_eq!(
+            Some(Winding::CounterClockwise),
+            Winding::of(&counter_clockwise)
+        );
+
+        assert!(!CullMode::Back.culls(&clockwise, Winding::Clockwise));
+        assert!(CullMode::Back.culls(&counter_clockwise, Winding::Clockwise));
+        assert!(CullMode::Front.culls(&clockwise, Winding::Clockwise));
+        assert!(!CullMode::None.culls(&counter_clockwise, Winding::Clockwise));
+    }
+}
@@ -0,0 +1,19 @@
+use crate::geometry::{culling::CullMode, primitives::Triangle};
+
+pub struct Mesh {
+    pub triangles: Vec<Triangle>,
+    /// Overrides the world's cull mode when set.
+    pub cull_mode: Option<CullMode>,
+    /// Index of the world texture modulating the triangle colors.
+    pub texture: Option<usize>,
+}
+
+impl Mesh {
+    pub fn new(triangles: Vec<Triangle>) -> Self {
+        Self {
+            triangles,
+            cull_mode: None,
+            texture: None,
+        }
+    }
+}
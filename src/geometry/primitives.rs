@@ -1,14 +1,26 @@
-use std::cell::OnceCell;
+use std::{
+    cell::OnceCell,
+    ops::{Add, AddAssign, Div, Mul, Sub},
+};
 
-use glam::{IVec2, Vec3};
+use glam::{IVec2, Vec2, Vec3, Vec4};
 
-use crate::{camera::Camera, shaders::VertexShader};
+use crate::{
+    geometry::clipping::clip_to_guard_band,
+    operations::Interpolant,
+    shaders::{InterpolationMode, VertexShader},
+};
 
 pub struct Triangle {
     pub v0: Vec3,
     pub v1: Vec3,
     pub v2: Vec3,
     pub color: Vec3,
+    /// Texture coordinates of `v0`, `v1` and `v2`.
+    pub uvs: [Vec2; 3],
+    /// Normals of `v0`, `v1` and `v2` for smooth shading, the face normal
+    /// is used when unset.
+    pub normals: Option<[Vec3; 3]>,
     normal: OnceCell<Vec3>,
 }
 
@@ -19,10 +31,22 @@ impl Triangle {
             v1,
             v2,
             color,
+            uvs: [Vec2::ZERO; 3],
+            normals: None,
             normal: OnceCell::new(),
         }
     }
 
+    pub fn with_uvs(mut self, uvs: [Vec2; 3]) -> Self {
+        self.uvs = uvs;
+        self
+    }
+
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Self {
+        self.normals = Some(normals);
+        self
+    }
+
     // This normal orientation is important,
     // a flipped normal will influence illumination model.
     pub fn get_normal(&self) -> Vec3 {
@@ -31,23 +55,67 @@ impl Triangle {
             .get_or_init(|| ((self.v2 - self.v0).cross(self.v1 - self.v0)).normalize())
     }
 
-    pub fn project_to_canvas(&self, camera: &Camera) -> Triangle2D {
-        let vs = camera.as_vertex_shader();
-        let v0 = Vertex::new(self.v0, self.get_normal(), self.color);
-        let v1 = Vertex::new(self.v1, self.get_normal(), self.color);
-        let v2 = Vertex::new(self.v2, self.get_normal(), self.color);
-        Triangle2D {
-            v0: vs.vertex_shader(&v0),
-            v1: vs.vertex_shader(&v1),
-            v2: vs.vertex_shader(&v2),
+    /// Shades, clips against the near plane and the guard band, and projects
+    /// the triangle onto a `viewport` sized canvas. The result is empty when the
+    /// triangle lies entirely behind the near plane.
+    pub fn project_to_canvas<VS: VertexShader>(
+        &self,
+        vs: &VS,
+        viewport: Vec2,
+        interpolation: InterpolationMode,
+    ) -> Polygon2D<VS::Varyings> {
+        let normals = self.normals.unwrap_or([self.get_normal(); 3]);
+        let clip_vertices = [
+            (self.v0, normals[0], self.uvs[0]),
+            (self.v1, normals[1], self.uvs[1]),
+            (self.v2, normals[2], self.uvs[2]),
+        ]
+        .map(|(point, normal, uv)| vs.vertex_shader(&Vertex::new(point, normal, self.color, uv)));
+        Polygon2D {
+            vertices: clip_to_guard_band(&clip_vertices)
+                .iter()
+                .map(|v| v.project(viewport, interpolation))
+                .collect(),
         }
     }
 }
 
-pub struct Triangle2D {
-    pub v0: Pixel,
-    pub v1: Pixel,
-    pub v2: Pixel,
+/// Convex screen-space polygon, a triangle possibly cut by clipping.
+pub struct Polygon2D<V = Varyings> {
+    pub vertices: Vec<ScreenVertex<V>>,
+}
+
+impl<V> Polygon2D<V> {
+    /// Inclusive range of pixels the polygon may touch, padded by one pixel
+    /// to account for samples away from pixel centers.
+    pub fn pixel_bounds(&self) -> Option<(IVec2, IVec2)> {
+        let points = self.vertices.iter().map(|v| v.point);
+        let min = points.clone().reduce(IVec2::min)?;
+        let max = points.reduce(IVec2::max)?;
+        Some((
+            (min >> SUBPIXEL_BITS as i32) - 1,
+            (max >> SUBPIXEL_BITS as i32) + 1,
+        ))
+    }
+}
+
+#[cfg(test)]
+impl Polygon2D {
+    /// Test polygon from fixed-point positions, at depth 0.5 with default
+    /// varyings.
+    pub(crate) fn from_points(points: &[IVec2]) -> Self {
+        Polygon2D {
+            vertices: points
+                .iter()
+                .map(|p| ScreenVertex {
+                    point: *p,
+                    depth: 0.5,
+                    z_recip: 1f32,
+                    varyings: Varyings::default(),
+                })
+                .collect(),
+        }
+    }
 }
 
 pub trait Vertices {
@@ -55,6 +123,14 @@ pub trait Vertices {
     fn vertices(&self) -> impl AsRef<[Self::Vertex]>;
 }
 
+impl<V: Vertices> Vertices for &V {
+    type Vertex = V::Vertex;
+
+    fn vertices(&self) -> impl AsRef<[Self::Vertex]> {
+        (*self).vertices()
+    }
+}
+
 impl Vertices for Triangle {
     type Vertex = Vec3;
 
@@ -63,33 +139,331 @@ impl Vertices for Triangle {
     }
 }
 
-impl Vertices for Triangle2D {
-    type Vertex = Pixel;
+impl<V> Vertices for Polygon2D<V> {
+    type Vertex = ScreenVertex<V>;
 
     fn vertices(&self) -> impl AsRef<[Self::Vertex]> {
-        [self.v0, self.v1, self.v2]
+        &self.vertices
+    }
+}
+
+/// Attributes interpolated from the vertex shader to the pixel shader.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Varyings {
+    /// Lit color with Gouraud shading, the surface reflectance with Phong
+    /// shading.
+    pub color: Vec3,
+    /// World space position.
+    pub position: Vec3,
+    /// World space normal, no longer unit length once interpolated.
+    pub normal: Vec3,
+    /// Texture coordinates.
+    pub uv: Vec2,
+}
+
+impl Varyings {
+    pub fn new(color: Vec3, position: Vec3, normal: Vec3, uv: Vec2) -> Self {
+        Self {
+            color,
+            position,
+            normal,
+            uv,
+        }
+    }
+}
+
+impl Interpolant for Varyings {
+    const CHANNELS: usize = 11;
+
+    fn lerp(&self, rhs: &Varyings, t: f32) -> Varyings {
+        Varyings::new(
+            self.color.lerp(rhs.color, t),
+            self.position.lerp(rhs.position, t),
+            self.normal.lerp(rhs.normal, t),
+            self.uv.lerp(rhs.uv, t),
+        )
+    }
+
+    fn channel(&self, c: usize) -> f32 {
+        match c {
+            0..3 => self.color[c],
+            3..6 => self.position[c - 3],
+            6..9 => self.normal[c - 6],
+            _ => self.uv[c - 9],
+        }
+    }
+
+    fn from_channels(mut channel: impl FnMut(usize) -> f32) -> Self {
+        Varyings::new(
+            Vec3::new(channel(0), channel(1), channel(2)),
+            Vec3::new(channel(3), channel(4), channel(5)),
+            Vec3::new(channel(6), channel(7), channel(8)),
+            Vec2::new(channel(9), channel(10)),
+        )
+    }
+}
+
+impl Add for Varyings {
+    type Output = Varyings;
+
+    fn add(self, rhs: Varyings) -> Varyings {
+        Varyings::new(
+            self.color + rhs.color,
+            self.position + rhs.position,
+            self.normal + rhs.normal,
+            self.uv + rhs.uv,
+        )
+    }
+}
+
+impl AddAssign for Varyings {
+    fn add_assign(&mut self, rhs: Varyings) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Varyings {
+    type Output = Varyings;
+
+    fn sub(self, rhs: Varyings) -> Varyings {
+        Varyings::new(
+            self.color - rhs.color,
+            self.position - rhs.position,
+            self.normal - rhs.normal,
+            self.uv - rhs.uv,
+        )
     }
 }
 
+impl Mul<f32> for Varyings {
+    type Output = Varyings;
+
+    fn mul(self, rhs: f32) -> Varyings {
+        Varyings::new(
+            self.color * rhs,
+            self.position * rhs,
+            self.normal * rhs,
+            self.uv * rhs,
+        )
+    }
+}
+
+impl Div<f32> for Varyings {
+    type Output = Varyings;
+
+    fn div(self, rhs: f32) -> Varyings {
+        Varyings::new(
+            self.color / rhs,
+            self.position / rhs,
+            self.normal / rhs,
+            self.uv / rhs,
+        )
+    }
+}
+
+/// Screen-space derivatives of a polygon's perspective divisor and
+/// premultiplied varyings, both linear across the polygon.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Gradients<V = Varyings> {
+    /// Change of `z_recip` per pixel along x and y.
+    pub z_recip: Vec2,
+    /// Change of the premultiplied varyings per pixel along x.
+    pub dx: V,
+    /// Change of the premultiplied varyings per pixel along y.
+    pub dy: V,
+}
+
+impl<V: Interpolant> Gradients<V> {
+    /// Gradients of the plane through the polygon's largest fan triangle,
+    /// zero when the polygon has no area.
+    pub fn of(vertices: &[ScreenVertex<V>]) -> Self {
+        let Some((first, rest)) = vertices.split_first() else {
+            return Self::default();
+        };
+        let to_pixels = |v: &ScreenVertex<V>| v.point.as_vec2() / SUBPIXEL_ONE as f32;
+        let Some((b, c)) = rest
+            .windows(2)
+            .map(|pair| (&pair[0], &pair[1]))
+            .max_by(|x, y| {
+                let area = |(b, c): &(&ScreenVertex<V>, &ScreenVertex<V>)| {
+                    (to_pixels(b) - to_pixels(first))
+                        .perp_dot(to_pixels(c) - to_pixels(first))
+                        .abs()
+                };
+                area(x).total_cmp(&area(y))
+            })
+        else {
+            return Self::default();
+        };
+        let (e1, e2) = (
+            to_pixels(b) - to_pixels(first),
+            to_pixels(c) - to_pixels(first),
+        );
+        let det = e1.perp_dot(e2);
+        if det.abs() <= f32::EPSILON {
+            return Self::default();
+        }
+        // Solves d = dx * e.x + dy * e.y along both edges.
+        fn solve<T: Interpolant>(d1: T, d2: T, e1: Vec2, e2: Vec2, det: f32) -> (T, T) {
+            ((d1 * e2.y - d2 * e1.y) / det, (d2 * e1.x - d1 * e2.x) / det)
+        }
+        let (z_dx, z_dy) = solve(
+            b.z_recip - first.z_recip,
+            c.z_recip - first.z_recip,
+            e1,
+            e2,
+            det,
+        );
+        let (dx, dy) = solve(
+            b.varyings - first.varyings,
+            c.varyings - first.varyings,
+            e1,
+            e2,
+            det,
+        );
+        Self {
+            z_recip: Vec2::new(z_dx, z_dy),
+            dx,
+            dy,
+        }
+    }
+
+    /// Derivatives along x and y of the perspective corrected `varyings`
+    /// of a fragment with the given `z_recip`.
+    pub fn derivatives(&self, varyings: &V, z_recip: f32) -> (V, V) {
+        (
+            (self.dx - *varyings * self.z_recip.x) / z_recip,
+            (self.dy - *varyings * self.z_recip.y) / z_recip,
+        )
+    }
+}
+
+/// Vertex shader output in homogeneous clip space.
 #[derive(Clone, Copy)]
-pub struct Pixel {
+pub struct ClipVertex<V = Varyings> {
+    pub position: Vec4,
+    pub varyings: V,
+}
+
+impl<V: Interpolant> ClipVertex<V> {
+    pub fn new(position: Vec4, varyings: V) -> Self {
+        Self { position, varyings }
+    }
+
+    pub fn lerp(&self, rhs: &ClipVertex<V>, t: f32) -> ClipVertex<V> {
+        ClipVertex::new(
+            self.position.lerp(rhs.position, t),
+            self.varyings.lerp(&rhs.varyings, t),
+        )
+    }
+
+    /// Perspective divide followed by the viewport transform.
+    pub fn project(&self, viewport: Vec2, interpolation: InterpolationMode) -> ScreenVertex<V> {
+        let w_recip = self.position.w.recip();
+        let ndc = self.position.truncate() * w_recip;
+        let point = (ndc.truncate() * 0.5 + 0.5) * viewport;
+        // Affine interpolation is the perspective correct one with w pinned to 1.
+        let z_recip = match interpolation {
+            InterpolationMode::Perspective => w_recip,
+            InterpolationMode::Affine => 1f32,
+        };
+        ScreenVertex {
+            point: (point * SUBPIXEL_ONE as f32).round().as_ivec2(),
+            depth: ndc.z,
+            z_recip,
+            varyings: self.varyings * z_recip,
+        }
+    }
+}
+
+/// Fractional bits of [`ScreenVertex`] positions.
+pub const SUBPIXEL_BITS: u32 = 8;
+pub const SUBPIXEL_ONE: i32 = 1 << SUBPIXEL_BITS;
+pub const SUBPIXEL_HALF: i32 = SUBPIXEL_ONE / 2;
+
+/// Projected polygon vertex.
+///
+/// Its position keeps [`SUBPIXEL_BITS`] fractional bits so that
+/// rasterizers can place edges between pixel centers, the integer part
+/// is the pixel whose top-left corner is at or before the vertex.
+#[derive(Clone, Copy)]
+pub struct ScreenVertex<V = Varyings> {
+    /// Fixed-point canvas position.
     pub point: IVec2,
+    pub depth: f32,
     pub z_recip: f32,
-    pub illumination: Vec3,
+    /// Varyings premultiplied by `z_recip`.
+    pub varyings: V,
 }
 
-impl Pixel {
-    pub fn new(point: IVec2, z_recip: f32, illumination: Vec3) -> Self {
+impl<V: Interpolant> ScreenVertex<V> {
+    /// Linear blend of the position and every attribute.
+    pub fn lerp(&self, rhs: &ScreenVertex<V>, t: f32) -> ScreenVertex<V> {
+        ScreenVertex {
+            point: self
+                .point
+                .as_vec2()
+                .lerp(rhs.point.as_vec2(), t)
+                .round()
+                .as_ivec2(),
+            depth: self.depth + (rhs.depth - self.depth) * t,
+            z_recip: self.z_recip + (rhs.z_recip - self.z_recip) * t,
+            varyings: self.varyings.lerp(&rhs.varyings, t),
+        }
+    }
+
+    /// Fragment for the pixel `point` carrying this vertex' attributes.
+    pub fn to_pixel(&self, point: IVec2) -> Pixel<V> {
+        Pixel::new(point, self.depth, self.z_recip, self.varyings)
+    }
+
+    /// Attributes at pixel `point` given its barycentric `weights`
+    /// relative to `a`, `b` and `c`.
+    pub fn barycentric(
+        point: IVec2,
+        weights: Vec3,
+        a: &ScreenVertex<V>,
+        b: &ScreenVertex<V>,
+        c: &ScreenVertex<V>,
+    ) -> Pixel<V> {
+        let depth_and_w = Vec3::new(a.depth, a.z_recip, 0f32) * weights.x
+            + Vec3::new(b.depth, b.z_recip, 0f32) * weights.y
+            + Vec3::new(c.depth, c.z_recip, 0f32) * weights.z;
+        let varyings = a.varyings * weights.x + b.varyings * weights.y + c.varyings * weights.z;
+        Pixel::new(point, depth_and_w.x, depth_and_w.y, varyings)
+    }
+}
+
+/// Fragment handed to pixel shaders.
+#[derive(Clone, Copy)]
+pub struct Pixel<V = Varyings> {
+    /// Whole pixel coordinates on the canvas.
+    pub point: IVec2,
+    /// Normalized device depth, 0 on the near plane and 1 on the far one.
+    pub depth: f32,
+    pub z_recip: f32,
+    /// Varyings premultiplied by `z_recip`, which keeps them linear in
+    /// screen space. See [`Pixel::corrected_varyings`].
+    pub varyings: V,
+}
+
+impl<V: Interpolant> Pixel<V> {
+    pub fn new(point: IVec2, depth: f32, z_recip: f32, varyings: V) -> Self {
         Self {
             point,
+            depth,
             z_recip,
-            illumination,
+            varyings,
         }
     }
 
-    pub fn xyz_as_vec3(&self) -> Vec3 {
+    pub fn corrected_varyings(&self) -> V {
+        self.varyings / self.z_recip
+    }
+
+    pub fn screen_as_vec4(&self) -> Vec4 {
         let point = self.point.as_vec2();
-        Vec3::new(point.x, point.y, self.z_recip)
+        Vec4::new(point.x, point.y, self.depth, self.z_recip)
     }
 }
 
@@ -97,14 +471,16 @@ pub struct Vertex {
     pub point: Vec3,
     pub normal: Vec3,
     pub reflectance: Vec3,
+    pub uv: Vec2,
 }
 
 impl Vertex {
-    pub fn new(point: Vec3, normal: Vec3, reflectance: Vec3) -> Self {
+    pub fn new(point: Vec3, normal: Vec3, reflectance: Vec3, uv: Vec2) -> Self {
         Self {
             point,
             normal,
             reflectance,
+            uv,
         }
     }
 }
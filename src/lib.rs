@@ -1,100 +1,1157 @@
-use std::{ops::DerefMut, slice::from_raw_parts_mut, sync::Mutex};
+use std::{
+    ffi::{CStr, c_char, c_void},
+    slice::{from_raw_parts, from_raw_parts_mut},
+    sync::Mutex,
+};
 
-use pixels::PixelBuffer;
-use world::World;
+use glam::Vec3;
 
+use callback::{FragmentCallback, RusterizerFragmentFn};
+use error::{Error, Result, RusterizerStatus, ffi_call};
+use geometry::{
+    culling::{CullMode, Winding},
+    mesh::Mesh,
+};
+use light::Light;
+use pixels::{PixelBuffer, PixelFormat};
+use resample::DownsampleFilter;
+use scene::obj;
+use shaders::{InterpolationMode, ShadingMode};
+use shadow::{ShadowSettings, check_pcf_radius};
+use texture::{AddressMode, Texture, TextureFilter};
+use world::{Msaa, Rasterizer, World};
+
+pub mod callback;
 pub mod camera;
+pub mod error;
 pub mod geometry;
-mod operations;
+pub mod light;
+pub mod operations;
 mod painter;
 pub mod pixels;
+pub mod resample;
 pub mod scene;
 pub mod shaders;
+pub mod shadow;
+pub mod span;
+pub mod texture;
 pub mod world;
 
-// Global Lock
-static WORLD: Mutex<Option<World>> = Mutex::new(None);
+/// Opaque handle owning one independent world.
+///
+/// Each handle carries its own lock, so different worlds can be driven
+/// from different threads at the same time, while calls on the same
+/// handle are serialized.
+pub struct RusterizerWorld {
+    world: Mutex<World>,
+}
+
+/// # Safety
+///
+/// `handle` must be null or a pointer returned by `rusterizer_world_create`
+/// that has not been destroyed yet.
+unsafe fn with_world<R>(
+    handle: *const RusterizerWorld,
+    f: impl FnOnce(&mut World) -> Result<R>,
+) -> Result<R> {
+    let handle = unsafe { handle.as_ref() }
+        .ok_or_else(|| Error::new(RusterizerStatus::NullHandle, "world handle is null"))?;
+    let mut guard = handle
+        .world
+        .lock()
+        .map_err(|_| Error::new(RusterizerStatus::LockPoisoned, "world lock is poisoned"))?;
+    f(&mut guard)
+}
+
+fn finite(name: &str, value: f32) -> Result<f32> {
+    if !value.is_finite() {
+        return Err(Error::invalid_argument(format!(
+            "{name} must be finite, got {value}"
+        )));
+    }
+    Ok(value)
+}
 
-fn with_world_opt_mut(f: impl FnOnce(&mut Option<World>)) -> bool {
-    let guard = WORLD.lock();
-    if guard.is_err() {
-        return false;
+fn finite_vec3(name: &str, x: f32, y: f32, z: f32) -> Result<Vec3> {
+    let v = Vec3::new(x, y, z);
+    if !v.is_finite() {
+        return Err(Error::invalid_argument(format!(
+            "{name} must be finite, got {v}"
+        )));
     }
-    let mut opt = guard.unwrap();
-    f(opt.deref_mut());
-    true
+    Ok(v)
 }
 
-fn with_world(f: impl FnOnce(&World)) -> bool {
-    let guard = WORLD.lock();
-    if guard.is_err() {
-        return false;
+fn light_mut(world: &mut World, index: u32) -> Result<&mut Light> {
+    let light_count = world.lighting().lights().len();
+    world
+        .lighting_mut()
+        .light_mut(index as usize)
+        .ok_or_else(|| {
+            Error::invalid_argument(format!(
+                "light index {index} out of range, world has {light_count} lights"
+            ))
+        })
+}
+
+/// Adds `light` to the world, writing its index to `out` unless null.
+unsafe fn add_light(world: &mut World, light: Light, out: *mut u32) {
+    let index = world.lighting_mut().add(light) as u32;
+    if let Some(out) = unsafe { out.as_mut() } {
+        *out = index;
     }
-    let opt = guard.unwrap();
-    if opt.is_none() {
-        return false;
+}
+
+fn mesh_mut(world: &mut World, index: u32) -> Result<&mut Mesh> {
+    let mesh_count = world.meshes().len();
+    world.mesh_mut(index as usize).ok_or_else(|| {
+        Error::invalid_argument(format!(
+            "mesh index {index} out of range, world has {mesh_count} meshes"
+        ))
+    })
+}
+
+fn check_texture_index(world: &World, index: u32) -> Result<usize> {
+    let texture_count = world.textures().len();
+    if index as usize >= texture_count {
+        return Err(Error::invalid_argument(format!(
+            "texture index {index} out of range, world has {texture_count} textures"
+        )));
     }
-    f(opt.as_ref().unwrap());
-    true
+    Ok(index as usize)
 }
 
-fn with_world_mut(f: impl FnOnce(&mut World)) -> bool {
-    let guard = WORLD.lock();
-    if guard.is_err() {
-        return false;
+fn texture_mut(world: &mut World, index: u32) -> Result<&mut Texture> {
+    let index = check_texture_index(world, index)?;
+    Ok(world.texture_mut(index).expect("texture index checked"))
+}
+
+/// Adds `texture` to the world, writing its index to `out` unless null.
+unsafe fn add_texture(world: &mut World, texture: Texture, out: *mut u32) {
+    let index = world.add_texture(texture) as u32;
+    if let Some(out) = unsafe { out.as_mut() } {
+        *out = index;
     }
-    let mut opt = guard.unwrap();
-    if opt.is_none() {
-        return false;
+}
+
+/// # Safety
+///
+/// `path` must be null or a NUL-terminated string.
+unsafe fn path_str<'a>(path: *const c_char) -> Result<&'a str> {
+    if path.is_null() {
+        return Err(Error::new(RusterizerStatus::NullPointer, "path is null"));
     }
-    f(opt.as_mut().unwrap());
-    true
+    unsafe { CStr::from_ptr(path) }
+        .to_str()
+        .map_err(|err| Error::invalid_argument(format!("path is not UTF-8: {err}")))
+}
+
+/// # Safety
+///
+/// `out` must be null or point to writable storage for one handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_world_create(
+    height: u32,
+    width: u32,
+    pixel_format: u32,
+    out: *mut *mut RusterizerWorld,
+) -> RusterizerStatus {
+    ffi_call(|| {
+        let out = unsafe { out.as_mut() }
+            .ok_or_else(|| Error::new(RusterizerStatus::NullPointer, "out pointer is null"))?;
+        if height == 0 || width == 0 {
+            return Err(Error::invalid_argument(format!(
+                "canvas size must be non-zero, got {width}x{height}"
+            )));
+        }
+        let pixel_format = PixelFormat::try_from(pixel_format)?;
+        let handle = RusterizerWorld {
+            world: Mutex::new(World::new(height, width, pixel_format)),
+        };
+        *out = Box::into_raw(Box::new(handle));
+        Ok(())
+    })
+}
+
+/// # Safety
+///
+/// `handle` must be null or a pointer returned by `rusterizer_world_create`,
+/// and must not be used by any thread after this call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_world_destroy(
+    handle: *mut RusterizerWorld,
+) -> RusterizerStatus {
+    ffi_call(|| {
+        if handle.is_null() {
+            return Err(Error::new(
+                RusterizerStatus::NullHandle,
+                "world handle is null",
+            ));
+        }
+        drop(unsafe { Box::from_raw(handle) });
+        Ok(())
+    })
+}
+
+/// # Safety
+///
+/// `handle` must be a live world handle, and it's caller's responsibility
+/// to provide a pixel buffer holding `height` rows of `pitch` bytes each
+/// (the last row may omit its padding), laid out in the world's pixel format.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_draw_to_pixel_buf(
+    handle: *const RusterizerWorld,
+    buf: *mut u8,
+    pitch: usize,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            if buf.is_null() {
+                return Err(Error::new(
+                    RusterizerStatus::NullPointer,
+                    "pixel buffer is null",
+                ));
+            }
+            let (height, width) = world.get_canvas_size();
+            let format = world.pixel_format();
+            let bytes_per_row = width as usize * format.bytes_per_pixel();
+            if pitch < bytes_per_row {
+                return Err(Error::invalid_argument(format!(
+                    "row pitch {pitch} is smaller than a {width} pixels row ({bytes_per_row} bytes)"
+                )));
+            }
+            let buf_len = PixelBuffer::required_len(width, height, pitch, format)?;
+            let buf = from_raw_parts_mut(buf, buf_len);
+            let pixel_buf = PixelBuffer::new(width, height, pitch, format, buf)?;
+            world.draw(pixel_buf);
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_camera_yaw(
+    handle: *const RusterizerWorld,
+    yaw: f32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            world.set_yaw(finite("yaw", yaw)?);
+            Ok(())
+        })
+    })
+}
+
+/// Replaces the camera orientation, applying yaw, then pitch, then roll.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_camera_set_orientation(
+    handle: *const RusterizerWorld,
+    yaw: f32,
+    pitch: f32,
+    roll: f32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            let (yaw, pitch, roll) = (
+                finite("yaw", yaw)?,
+                finite("pitch", pitch)?,
+                finite("roll", roll)?,
+            );
+            world.camera_mut().set_orientation(yaw, pitch, roll);
+            Ok(())
+        })
+    })
+}
+
+/// Rotates the camera relative to its current orientation,
+/// around its own axes.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_camera_rotate(
+    handle: *const RusterizerWorld,
+    yaw: f32,
+    pitch: f32,
+    roll: f32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            let (yaw, pitch, roll) = (
+                finite("yaw", yaw)?,
+                finite("pitch", pitch)?,
+                finite("roll", roll)?,
+            );
+            let camera = world.camera_mut();
+            camera.yaw(yaw);
+            camera.pitch(pitch);
+            camera.roll(roll);
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_camera_set_position(
+    handle: *const RusterizerWorld,
+    x: f32,
+    y: f32,
+    z: f32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            world
+                .camera_mut()
+                .set_position(finite_vec3("position", x, y, z)?);
+            Ok(())
+        })
+    })
+}
+
+/// Moves the camera by an offset expressed in world space.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_camera_translate(
+    handle: *const RusterizerWorld,
+    x: f32,
+    y: f32,
+    z: f32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            world
+                .camera_mut()
+                .translate(finite_vec3("offset", x, y, z)?);
+            Ok(())
+        })
+    })
+}
+
+/// Moves the camera by an offset expressed in camera space
+/// (right, down, forward).
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_camera_move_local(
+    handle: *const RusterizerWorld,
+    right: f32,
+    down: f32,
+    forward: f32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            world
+                .camera_mut()
+                .move_local(finite_vec3("offset", right, down, forward)?);
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn rusterizer_camera_look_at(
+    handle: *const RusterizerWorld,
+    eye_x: f32,
+    eye_y: f32,
+    eye_z: f32,
+    target_x: f32,
+    target_y: f32,
+    target_z: f32,
+    up_x: f32,
+    up_y: f32,
+    up_z: f32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            let eye = finite_vec3("eye", eye_x, eye_y, eye_z)?;
+            let target = finite_vec3("target", target_x, target_y, target_z)?;
+            let up = finite_vec3("up", up_x, up_y, up_z)?;
+            world.camera_mut().set_look_at(eye, target, up)
+        })
+    })
+}
+
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_camera_set_perspective(
+    handle: *const RusterizerWorld,
+    fov_y: f32,
+    near: f32,
+    far: f32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            world.camera_mut().set_perspective(fov_y, near, far)
+        })
+    })
+}
+
+/// Switches to a parallel projection showing `height` world units vertically.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_camera_set_orthographic(
+    handle: *const RusterizerWorld,
+    height: f32,
+    near: f32,
+    far: f32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            world.camera_mut().set_orthographic(height, near, far)
+        })
+    })
+}
+
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_camera_set_aspect(
+    handle: *const RusterizerWorld,
+    aspect: f32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe { with_world(handle, |world| world.camera_mut().set_aspect(aspect)) })
+}
+
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_set_cull_mode(
+    handle: *const RusterizerWorld,
+    cull_mode: u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            world.set_cull_mode(CullMode::try_from(cull_mode)?);
+            Ok(())
+        })
+    })
+}
+
+/// Sets which on-screen winding (y pointing down) counts as front facing.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_set_front_face(
+    handle: *const RusterizerWorld,
+    winding: u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            world.set_front_face(Winding::try_from(winding)?);
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+///
+/// `handle` must be a live world handle and `out` must point to writable storage.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_mesh_count(
+    handle: *const RusterizerWorld,
+    out: *mut u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            let out = out
+                .as_mut()
+                .ok_or_else(|| Error::new(RusterizerStatus::NullPointer, "out pointer is null"))?;
+            *out = world.meshes().len() as u32;
+            Ok(())
+        })
+    })
+}
+
+/// Overrides the world's cull mode for one mesh.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_mesh_set_cull_mode(
+    handle: *const RusterizerWorld,
+    mesh_index: u32,
+    cull_mode: u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            let cull_mode = CullMode::try_from(cull_mode)?;
+            mesh_mut(world, mesh_index)?.cull_mode = Some(cull_mode);
+            Ok(())
+        })
+    })
+}
+
+/// Makes the mesh follow the world's cull mode again.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_mesh_reset_cull_mode(
+    handle: *const RusterizerWorld,
+    mesh_index: u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            mesh_mut(world, mesh_index)?.cull_mode = None;
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_set_interpolation_mode(
+    handle: *const RusterizerWorld,
+    mode: u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            world.set_interpolation_mode(InterpolationMode::try_from(mode)?);
+            Ok(())
+        })
+    })
+}
+
+/// Selects between per vertex (Gouraud) and per pixel (Phong) lighting.
+///
+/// Worlds start with Gouraud shading, which draws no shadows: select Phong
+/// shading to see the ones enabled with [`rusterizer_set_shadows`].
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_set_shading_mode(
+    handle: *const RusterizerWorld,
+    mode: u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            world.set_shading_mode(ShadingMode::try_from(mode)?);
+            Ok(())
+        })
+    })
+}
+
+/// Casts shadows from every light with `map_size` texels wide shadow maps,
+/// filtered over a `pcf_radius` texels neighbourhood. A `map_size` of 0
+/// disables shadows, which are only drawn with Phong shading. Worlds start
+/// without shadows. The radius is validated either way.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_set_shadows(
+    handle: *const RusterizerWorld,
+    map_size: u32,
+    pcf_radius: u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            check_pcf_radius(pcf_radius)?;
+            let settings = match map_size {
+                0 => None,
+                _ => Some(ShadowSettings::new(map_size, pcf_radius)?),
+            };
+            world.set_shadows(settings);
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_set_rasterizer(
+    handle: *const RusterizerWorld,
+    rasterizer: u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            world.set_rasterizer(Rasterizer::try_from(rasterizer)?);
+            Ok(())
+        })
+    })
+}
+
+/// Sets the MSAA sample count, 1 (or 0) disables multisampling.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_set_msaa(
+    handle: *const RusterizerWorld,
+    samples: u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            world.set_msaa(Msaa::try_from(samples)?);
+            Ok(())
+        })
+    })
+}
+
+/// Renders at `factor` times the canvas resolution on each axis, then
+/// shrinks the result with `filter`. A factor of 1 disables supersampling.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_set_supersampling(
+    handle: *const RusterizerWorld,
+    factor: u32,
+    filter: u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            world.set_supersampling(factor, DownsampleFilter::try_from(filter)?)
+        })
+    })
+}
+
+/// Splits rendering into screen tiles drawn by `threads` threads, 1 renders
+/// on the calling thread and 0 uses one thread per available core.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_set_thread_count(
+    handle: *const RusterizerWorld,
+    threads: u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe { with_world(handle, |world| world.set_thread_count(threads)) })
 }
 
+/// # Safety
+///
+/// `handle` must be a live world handle and `out` must point to writable storage.
 #[unsafe(no_mangle)]
-pub extern "C" fn rusterizer_init_scene(height: u32, width: u32) -> bool {
-    with_world_opt_mut(|world_opt| {
-        world_opt.replace(World::new(height, width));
+pub unsafe extern "C" fn rusterizer_light_count(
+    handle: *const RusterizerWorld,
+    out: *mut u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            let out = out
+                .as_mut()
+                .ok_or_else(|| Error::new(RusterizerStatus::NullPointer, "out pointer is null"))?;
+            *out = world.lighting().lights().len() as u32;
+            Ok(())
+        })
     })
 }
 
+/// # Safety
+///
+/// `handle` must be a live world handle and `out` either null or pointing
+/// to writable storage.
 #[unsafe(no_mangle)]
-pub extern "C" fn rusterizer_deinit_world() -> bool {
-    with_world_opt_mut(|world_opt| {
-        let prev_world = world_opt.take();
-        drop(prev_world);
+pub unsafe extern "C" fn rusterizer_light_add_point(
+    handle: *const RusterizerWorld,
+    x: f32,
+    y: f32,
+    z: f32,
+    r: f32,
+    g: f32,
+    b: f32,
+    out: *mut u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            let light = Light::point(finite_vec3("position", x, y, z)?, Vec3::new(r, g, b))?;
+            add_light(world, light, out);
+            Ok(())
+        })
     })
 }
 
+/// Adds a light shining along `(dx, dy, dz)` with irradiance `(r, g, b)`.
+///
 /// # Safety
 ///
-/// It's caller's responsibility to provide a pixel buffer that
-/// can contain pixel_num * 4 bytes data.
+/// `handle` must be a live world handle and `out` either null or pointing
+/// to writable storage.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn rusterizer_draw_to_pixel_buf(buf: *mut u8) -> bool {
-    with_world(|world| {
-        // TODO: assuming ARGB here (32bits), can make it configurable.
-        //let buf_len = world.get_canvas_size() as usize * 4;
-        let (height, width) = world.get_canvas_size();
-        let buf_len = (height * width) as usize * 4;
-        let buf = unsafe { from_raw_parts_mut(buf, buf_len) };
-        let pixel_buf = PixelBuffer::new(height, buf);
-        world.draw(pixel_buf);
+pub unsafe extern "C" fn rusterizer_light_add_directional(
+    handle: *const RusterizerWorld,
+    dx: f32,
+    dy: f32,
+    dz: f32,
+    r: f32,
+    g: f32,
+    b: f32,
+    out: *mut u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            let light = Light::directional(Vec3::new(dx, dy, dz), Vec3::new(r, g, b))?;
+            add_light(world, light, out);
+            Ok(())
+        })
     })
 }
 
+/// Adds a spotlight, `inner` and `outer` being the cone half angles in
+/// radians.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle and `out` either null or pointing
+/// to writable storage.
 #[unsafe(no_mangle)]
-pub extern "C" fn rusterizer_camera_yaw(yaw: f32) {
-    with_world_mut(|world| world.set_yaw(yaw));
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn rusterizer_light_add_spot(
+    handle: *const RusterizerWorld,
+    x: f32,
+    y: f32,
+    z: f32,
+    dx: f32,
+    dy: f32,
+    dz: f32,
+    r: f32,
+    g: f32,
+    b: f32,
+    inner: f32,
+    outer: f32,
+    out: *mut u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            let light = Light::spot(
+                finite_vec3("position", x, y, z)?,
+                Vec3::new(dx, dy, dz),
+                Vec3::new(r, g, b),
+                inner,
+                outer,
+            )?;
+            add_light(world, light, out);
+            Ok(())
+        })
+    })
+}
+
+/// Removes a light, the indices of the following ones shift down by one.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_light_remove(
+    handle: *const RusterizerWorld,
+    light_index: u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            light_mut(world, light_index)?;
+            world.lighting_mut().remove(light_index as usize);
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_light_set_enabled(
+    handle: *const RusterizerWorld,
+    light_index: u32,
+    enabled: bool,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            light_mut(world, light_index)?.set_enabled(enabled);
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_light_set_position(
+    handle: *const RusterizerWorld,
+    light_index: u32,
+    x: f32,
+    y: f32,
+    z: f32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            let position = finite_vec3("position", x, y, z)?;
+            light_mut(world, light_index)?.set_position(position);
+            Ok(())
+        })
+    })
+}
+
+/// Moves a light by an offset expressed in world space.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_light_translate(
+    handle: *const RusterizerWorld,
+    light_index: u32,
+    x: f32,
+    y: f32,
+    z: f32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            let offset = finite_vec3("offset", x, y, z)?;
+            light_mut(world, light_index)?.translate(offset);
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_light_set_direction(
+    handle: *const RusterizerWorld,
+    light_index: u32,
+    x: f32,
+    y: f32,
+    z: f32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            light_mut(world, light_index)?.set_direction(Vec3::new(x, y, z))
+        })
+    })
+}
+
+/// Sets the radiant power of a light per color channel, the irradiance
+/// of directional lights.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_light_set_power(
+    handle: *const RusterizerWorld,
+    light_index: u32,
+    r: f32,
+    g: f32,
+    b: f32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            light_mut(world, light_index)?.set_power(Vec3::new(r, g, b))
+        })
+    })
+}
+
+/// Sets the spot cone half angles of a light, in radians.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_light_set_cone(
+    handle: *const RusterizerWorld,
+    light_index: u32,
+    inner: f32,
+    outer: f32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            light_mut(world, light_index)?.set_cone(inner, outer)
+        })
+    })
+}
+
+/// Sets the indirect light power per area reaching every surface.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_set_ambient_light(
+    handle: *const RusterizerWorld,
+    r: f32,
+    g: f32,
+    b: f32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            world.lighting_mut().set_ambient(Vec3::new(r, g, b))
+        })
+    })
+}
+
+/// Adds a texture made of `height` rows of `width` RGBA texels, 8 bits per
+/// channel, rows being `pitch` bytes apart. Alpha is ignored.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle, `data` must point to `height`
+/// rows of `pitch` bytes each (the last row may omit its padding), and
+/// `out` must be either null or pointing to writable storage.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_texture_create_rgba8(
+    handle: *const RusterizerWorld,
+    width: u32,
+    height: u32,
+    data: *const u8,
+    pitch: usize,
+    out: *mut u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            if data.is_null() {
+                return Err(Error::new(
+                    RusterizerStatus::NullPointer,
+                    "texture data is null",
+                ));
+            }
+            let row_len = width as usize * 4;
+            if pitch < row_len {
+                return Err(Error::invalid_argument(format!(
+                    "row pitch {pitch} is smaller than a {width} texels row ({row_len} bytes)"
+                )));
+            }
+            let data = from_raw_parts(data, Texture::rgba8_len(width, height, pitch)?);
+            let texture = Texture::from_rgba8(width, height, pitch, data)?;
+            add_texture(world, texture, out);
+            Ok(())
+        })
+    })
+}
+
+/// Adds a texture loaded from a PPM or TGA image file.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle, `path` a NUL-terminated string
+/// and `out` either null or pointing to writable storage.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_texture_load(
+    handle: *const RusterizerWorld,
+    path: *const c_char,
+    out: *mut u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            add_texture(world, Texture::load(path_str(path)?)?, out);
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_texture_set_address_mode(
+    handle: *const RusterizerWorld,
+    texture_index: u32,
+    address_mode: u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            let address_mode = AddressMode::try_from(address_mode)?;
+            texture_mut(world, texture_index)?.set_address_mode(address_mode);
+            Ok(())
+        })
+    })
+}
+
+/// Mipmaps are built when the texture is added, whatever the filter.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_texture_set_filter(
+    handle: *const RusterizerWorld,
+    texture_index: u32,
+    filter: u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            let filter = TextureFilter::try_from(filter)?;
+            texture_mut(world, texture_index)?.set_filter(filter);
+            Ok(())
+        })
+    })
+}
+
+/// Modulates the colors of a mesh by a texture.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_mesh_set_texture(
+    handle: *const RusterizerWorld,
+    mesh_index: u32,
+    texture_index: u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            let texture = check_texture_index(world, texture_index)?;
+            mesh_mut(world, mesh_index)?.texture = Some(texture);
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_mesh_reset_texture(
+    handle: *const RusterizerWorld,
+    mesh_index: u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            mesh_mut(world, mesh_index)?.texture = None;
+            Ok(())
+        })
+    })
+}
+
+/// Installs `callback`, called with `user_data` to recolor every fragment
+/// of the following draws, or removes it when `callback` is null.
+///
+/// The callback runs on the render threads, concurrently when the world
+/// renders with several, while the world is locked: it must not call any
+/// function with this handle. It is not called for shadow maps.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle. `callback` must be safe to call
+/// concurrently from any thread with `user_data`, which must stay valid
+/// until the callback is replaced or removed, or the world destroyed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_set_fragment_callback(
+    handle: *const RusterizerWorld,
+    callback: Option<RusterizerFragmentFn>,
+    user_data: *mut c_void,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            world.set_fragment_callback(
+                callback.map(|callback| FragmentCallback::new(callback, user_data)),
+            );
+            Ok(())
+        })
+    })
+}
+
+/// Adds one mesh per group of a Wavefront OBJ file, colored by the diffuse
+/// colors of its MTL materials. `out`, when not null, receives the index
+/// of the first new mesh; files without faces add none.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle, `path` a NUL-terminated string
+/// and `out` either null or pointing to writable storage.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_load_obj(
+    handle: *const RusterizerWorld,
+    path: *const c_char,
+    out: *mut u32,
+) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            let meshes = obj::load(path_str(path)?)?;
+            if let Some(out) = out.as_mut() {
+                *out = world.meshes().len() as u32;
+            }
+            for mesh in meshes {
+                world.add_mesh(mesh);
+            }
+            Ok(())
+        })
+    })
+}
+
+/// Removes every mesh, the Cornell box included, for instance before
+/// loading a model with `rusterizer_load_obj`.
+///
+/// # Safety
+///
+/// `handle` must be a live world handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rusterizer_mesh_clear(handle: *const RusterizerWorld) -> RusterizerStatus {
+    ffi_call(|| unsafe {
+        with_world(handle, |world| {
+            world.clear_meshes();
+            Ok(())
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::ptr::{NonNull, null_mut};
+
+    use super::{
+        RusterizerStatus, rusterizer_draw_to_pixel_buf, rusterizer_world_create,
+        rusterizer_world_destroy,
+    };
+
+    #[test]
+    fn overflowing_pitch_is_rejected_before_drawing() {
+        let mut world = null_mut();
+        unsafe {
+            let status = rusterizer_world_create(4, 4, 0, &mut world);
+            assert_eq!(RusterizerStatus::Ok, status);
+            // Never dereferenced, the length check fails first.
+            let buf = NonNull::dangling().as_ptr();
+            let status = rusterizer_draw_to_pixel_buf(world, buf, usize::MAX / 2 + 1);
+            assert_eq!(RusterizerStatus::InvalidArgument, status);
+            // The world lock is not poisoned.
+            let mut pixels = [0u8; 64];
+            let status = rusterizer_draw_to_pixel_buf(world, pixels.as_mut_ptr(), 16);
+            assert_eq!(RusterizerStatus::Ok, status);
+            assert_eq!(RusterizerStatus::Ok, rusterizer_world_destroy(world));
+        }
+    }
 }
-//
-//#[unsafe(no_mangle)]
-//pub extern "C" fn as_camera_ztranslate(amount: f32) {
-//    with_world_mut(|world| world.camera.set_z_translate(amount));
-//}
-//
-//#[unsafe(no_mangle)]
-//pub extern "C" fn as_light_position_offset(x: f32, y: f32, z: f32) {
-//    with_world_mut(|world| world.light.update_offset(x, y, z));
-//}
-//
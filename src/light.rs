@@ -0,0 +1,315 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use glam::Vec3;
+
+use crate::error::{Error, Result};
+
+/// How a light spreads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightKind {
+    /// Shines from a position in every direction, falling off with the
+    /// square of the distance.
+    Point,
+    /// Parallel rays along a direction without falloff, like the sun.
+    Directional,
+    /// A point light restricted to a cone around its direction.
+    Spot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    kind: LightKind,
+    enabled: bool,
+    /// Ignored by directional lights.
+    position: Vec3,
+    /// Unit direction the light travels along, ignored by point lights.
+    direction: Vec3,
+    /// Radiant power per color channel, the irradiance of directional
+    /// lights.
+    power: Vec3,
+    /// Cosines of the spot cone half angles, full intensity inside the
+    /// inner one and none outside of the outer one.
+    cos_inner: f32,
+    cos_outer: f32,
+}
+
+impl Default for Light {
+    /// Point light just below the ceiling of the Cornell box.
+    fn default() -> Self {
+        Self {
+            kind: LightKind::Point,
+            enabled: true,
+            position: Vec3::new(0f32, -0.5, -0.7),
+            direction: Vec3::Y,
+            power: Vec3::splat(14f32),
+            cos_inner: 1f32,
+            cos_outer: 1f32,
+        }
+    }
+}
+
+impl Light {
+    pub fn point(position: Vec3, power: Vec3) -> Result<Self> {
+        let mut light = Light {
+            position,
+            ..Light::default()
+        };
+        light.set_power(power)?;
+        Ok(light)
+    }
+
+    pub fn directional(direction: Vec3, irradiance: Vec3) -> Result<Self> {
+        let mut light = Light {
+            kind: LightKind::Directional,
+            ..Light::default()
+        };
+        light.set_direction(direction)?;
+        light.set_power(irradiance)?;
+        Ok(light)
+    }
+
+    /// Spotlight with cone half angles `inner` and `outer`, in radians.
+    pub fn spot(
+        position: Vec3,
+        direction: Vec3,
+        power: Vec3,
+        inner: f32,
+        outer: f32,
+    ) -> Result<Self> {
+        let mut light = Light {
+            kind: LightKind::Spot,
+            position,
+            ..Light::default()
+        };
+        light.set_direction(direction)?;
+        light.set_power(power)?;
+        light.set_cone(inner, outer)?;
+        Ok(light)
+    }
+
+    /// Irradiance reaching a surface point with unit `normal`, zero when
+    /// the light is disabled.
+    pub fn irradiance(&self, position: Vec3, normal: Vec3) -> Vec3 {
+        if !self.enabled {
+            return Vec3::ZERO;
+        }
+        if self.kind == LightKind::Directional {
+            return (-self.direction).dot(normal).max(0f32) * self.power;
+        }
+        let r = self.position - position;
+        let to_light = r.normalize();
+        let d = to_light.dot(normal).max(0f32) / (4f32 * PI * r.dot(r)) * self.power;
+        match self.kind {
+            LightKind::Spot => d * self.cone_falloff(-to_light),
+            _ => d,
+        }
+    }
+
+    /// Smooth transition from the outer to the inner cone.
+    fn cone_falloff(&self, light_to_point: Vec3) -> f32 {
+        let cos = self.direction.dot(light_to_point);
+        let t = ((cos - self.cos_outer) / (self.cos_inner - self.cos_outer).max(f32::EPSILON))
+            .clamp(0f32, 1f32);
+        t * t * (3f32 - 2f32 * t)
+    }
+
+    pub fn kind(&self) -> LightKind {
+        self.kind
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Vec3) {
+        self.position = position;
+    }
+
+    pub fn translate(&mut self, offset: Vec3) {
+        self.position += offset;
+    }
+
+    pub fn direction(&self) -> Vec3 {
+        self.direction
+    }
+
+    pub fn set_direction(&mut self, direction: Vec3) -> Result<()> {
+        self.direction = direction.try_normalize().ok_or_else(|| {
+            Error::invalid_argument(format!(
+                "light direction must be finite and non-zero, got {direction}"
+            ))
+        })?;
+        Ok(())
+    }
+
+    pub fn power(&self) -> Vec3 {
+        self.power
+    }
+
+    pub fn set_power(&mut self, power: Vec3) -> Result<()> {
+        self.power = non_negative("light power", power)?;
+        Ok(())
+    }
+
+    /// Spot cone half angles, in radians.
+    pub fn cone(&self) -> (f32, f32) {
+        (self.cos_inner.acos(), self.cos_outer.acos())
+    }
+
+    /// Sets the spot cone half angles, in radians.
+    pub fn set_cone(&mut self, inner: f32, outer: f32) -> Result<()> {
+        if !(0f32 <= inner && inner <= outer && outer <= FRAC_PI_2) {
+            return Err(Error::invalid_argument(format!(
+                "spot cone angles must satisfy 0 <= inner <= outer <= pi/2, got inner {inner} and outer {outer}"
+            )));
+        }
+        self.cos_inner = inner.cos();
+        self.cos_outer = outer.cos();
+        Ok(())
+    }
+}
+
+/// Every light of a scene, plus an ambient term standing in for indirect
+/// lighting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lighting {
+    lights: Vec<Light>,
+    /// Indirect light power per area reaching every surface.
+    ambient: Vec3,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            lights: vec![Light::default()],
+            ambient: Vec3::splat(0.5),
+        }
+    }
+}
+
+impl Lighting {
+    /// Color of a surface point with unit `normal`, summing every enabled
+    /// light.
+    pub fn illuminate(&self, position: Vec3, normal: Vec3, reflectance: Vec3) -> Vec3 {
+        self.illuminate_with(position, normal, reflectance, |_| 1f32)
+    }
+
+    /// Like [`Lighting::illuminate`], scaling light `i` by `visibility(i)`.
+    /// It is only called for lights actually reaching the point.
+    pub fn illuminate_with(
+        &self,
+        position: Vec3,
+        normal: Vec3,
+        reflectance: Vec3,
+        visibility: impl Fn(usize) -> f32,
+    ) -> Vec3 {
+        let direct: Vec3 = self
+            .lights
+            .iter()
+            .enumerate()
+            .map(|(i, light)| match light.irradiance(position, normal) {
+                Vec3::ZERO => Vec3::ZERO,
+                irradiance => irradiance * visibility(i),
+            })
+            .sum();
+        reflectance * (direct + self.ambient)
+    }
+
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    pub fn light_mut(&mut self, index: usize) -> Option<&mut Light> {
+        self.lights.get_mut(index)
+    }
+
+    /// Adds `light` and returns its index.
+    pub fn add(&mut self, light: Light) -> usize {
+        self.lights.push(light);
+        self.lights.len() - 1
+    }
+
+    /// Removes the light at `index`, shifting the following ones down.
+    pub fn remove(&mut self, index: usize) -> Option<Light> {
+        (index < self.lights.len()).then(|| self.lights.remove(index))
+    }
+
+    pub fn ambient(&self) -> Vec3 {
+        self.ambient
+    }
+
+    pub fn set_ambient(&mut self, ambient: Vec3) -> Result<()> {
+        self.ambient = non_negative("ambient light", ambient)?;
+        Ok(())
+    }
+}
+
+fn non_negative(name: &str, v: Vec3) -> Result<Vec3> {
+    if !(v.is_finite() && v.cmpge(Vec3::ZERO).all()) {
+        return Err(Error::invalid_argument(format!(
+            "{name} must be finite and non-negative, got {v}"
+        )));
+    }
+    Ok(v)
+}
+
+#[cfg(test)]
+mod test {
+    use std::f32::consts::FRAC_PI_4;
+
+    use glam::Vec3;
+
+    use super::{Light, Lighting};
+
+    #[test]
+    fn inverse_square_falloff() {
+        let light = Light::point(Vec3::ZERO, Vec3::splat(8f32)).unwrap();
+        let at = |distance: f32| {
+            light
+                .irradiance(Vec3::new(0f32, distance, 0f32), Vec3::NEG_Y)
+                .x
+        };
+        assert!((at(1f32) / at(2f32) - 4f32).abs() < 1e-5);
+        // Surfaces facing away are not lit.
+        assert_eq!(Vec3::ZERO, light.irradiance(Vec3::Y, Vec3::Y));
+        assert!(Light::point(Vec3::ZERO, Vec3::splat(-1f32)).is_err());
+    }
+
+    #[test]
+    fn directional_and_spot() {
+        let sun = Light::directional(Vec3::new(0f32, 2f32, 0f32), Vec3::ONE).unwrap();
+        let far_away = Vec3::new(100f32, 0f32, -50f32);
+        assert_eq!(Vec3::ONE, sun.irradiance(far_away, Vec3::NEG_Y));
+
+        let spot =
+            Light::spot(Vec3::ZERO, Vec3::Y, Vec3::ONE, FRAC_PI_4 / 2f32, FRAC_PI_4).unwrap();
+        let facing_light = Vec3::NEG_Y;
+        let inside = spot.irradiance(Vec3::Y, facing_light);
+        let edge = spot.irradiance(Vec3::new(0.5, 1f32, 0f32).normalize(), facing_light);
+        let outside = spot.irradiance(Vec3::X, Vec3::NEG_X);
+        assert!(inside.x > edge.x && edge.x > 0f32);
+        assert_eq!(Vec3::ZERO, outside);
+    }
+
+    #[test]
+    fn lights_accumulate_unless_disabled() {
+        let mut lighting = Lighting::default();
+        lighting.set_ambient(Vec3::ZERO).unwrap();
+        lighting.light_mut(0).unwrap().set_enabled(false);
+        let sun = Light::directional(Vec3::NEG_Y, Vec3::ONE).unwrap();
+        let first = lighting.add(sun);
+        lighting.add(sun);
+        let lit = |lighting: &Lighting| lighting.illuminate(Vec3::ZERO, Vec3::Y, Vec3::ONE);
+        assert_eq!(Vec3::splat(2f32), lit(&lighting));
+        lighting.light_mut(first).unwrap().set_enabled(false);
+        assert_eq!(Vec3::ONE, lit(&lighting));
+    }
+}
@@ -1,9 +1,9 @@
 use std::{
     iter::Map,
-    ops::AddAssign,
+    ops::{Add, AddAssign, Div, Mul, Sub},
 };
 
-use glam::{IVec2, Vec2, Vec3};
+use glam::{IVec2, Vec2, Vec3, Vec4};
 
 use crate::geometry::primitives::Pixel;
 
@@ -12,6 +12,65 @@ pub trait Interpolate<RHS> {
     fn interpolate(&self, rhs: &RHS, result_size: usize) -> Self::Output;
 }
 
+/// Attributes interpolated linearly in screen space, from vertex shaders
+/// to fragment shaders. Any type with vector space operations and made of
+/// floats, which the span kernels interpolate one by one, qualifies and
+/// gets [`Interpolate`] with it.
+pub trait Interpolant:
+    Copy
+    + Default
+    + Send
+    + Sync
+    + Add<Output = Self>
+    + AddAssign
+    + Sub<Output = Self>
+    + Mul<f32, Output = Self>
+    + Div<f32, Output = Self>
+{
+    /// Number of floats making up a value, see [`Interpolant::channel`].
+    const CHANNELS: usize;
+
+    fn lerp(&self, rhs: &Self, t: f32) -> Self {
+        *self + (*rhs - *self) * t
+    }
+
+    /// Float `c` of the value, for `c` below [`Interpolant::CHANNELS`].
+    fn channel(&self, c: usize) -> f32;
+
+    /// The value whose float `c` is `channel(c)`.
+    fn from_channels(channel: impl FnMut(usize) -> f32) -> Self;
+}
+
+impl Interpolant for f32 {
+    const CHANNELS: usize = 1;
+
+    fn channel(&self, _c: usize) -> f32 {
+        *self
+    }
+
+    fn from_channels(mut channel: impl FnMut(usize) -> f32) -> Self {
+        channel(0)
+    }
+}
+
+macro_rules! impl_interpolant_for_vec {
+    ($($vec:ty => $channels:literal),*) => {$(
+        impl Interpolant for $vec {
+            const CHANNELS: usize = $channels;
+
+            fn channel(&self, c: usize) -> f32 {
+                self[c]
+            }
+
+            fn from_channels(channel: impl FnMut(usize) -> f32) -> Self {
+                Self::from_array(std::array::from_fn(channel))
+            }
+        }
+    )*};
+}
+
+impl_interpolant_for_vec!(Vec2 => 2, Vec3 => 3, Vec4 => 4);
+
 pub struct LinePoints<T> {
     cursor: T,
     step: T,
@@ -32,25 +91,13 @@ impl<AddAssignable: AddAssign + Copy> Iterator for LinePoints<AddAssignable> {
     }
 }
 
-impl Interpolate<Vec2> for Vec2 {
-    type Output = LinePoints<Vec2>;
-
-    fn interpolate(&self, rhs: &Vec2, result_size: usize) -> Self::Output {
-        LinePoints {
-            cursor: *self,
-            step: (rhs - self) / std::cmp::max(result_size - 1, 1) as f32,
-            rounds: result_size,
-        }
-    }
-}
-
-impl Interpolate<Vec3> for Vec3 {
-    type Output = LinePoints<Vec3>;
+impl<T: Interpolant> Interpolate<T> for T {
+    type Output = LinePoints<T>;
 
-    fn interpolate(&self, rhs: &Vec3, result_size: usize) -> Self::Output {
+    fn interpolate(&self, rhs: &T, result_size: usize) -> Self::Output {
         LinePoints {
             cursor: *self,
-            step: (rhs - self) / std::cmp::max(result_size - 1, 1) as f32,
+            step: (*rhs - *self) / std::cmp::max(result_size - 1, 1) as f32,
             rounds: result_size,
         }
     }
@@ -72,35 +119,36 @@ fn cast_vec2_to_ivec2(v: Vec2) -> IVec2 {
     IVec2::new(v.x.round() as i32, v.y.round() as i32)
 }
 
-pub struct InterPixels {
-    pos_and_z_iter: LinePoints<Vec3>,
-    illu_iter: LinePoints<Vec3>,
+pub struct InterPixels<V> {
+    screen_iter: LinePoints<Vec4>,
+    varyings_iter: LinePoints<V>,
 }
 
-impl Iterator for InterPixels {
-    type Item = Pixel;
+impl<V: Interpolant> Iterator for InterPixels<V> {
+    type Item = Pixel<V>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match (self.pos_and_z_iter.next(), self.illu_iter.next()) {
-            (Some(pos_and_z), Some(illumination)) => Some(Pixel::new(
-                IVec2::new(pos_and_z.x.round() as i32, pos_and_z.y.round() as i32),
-                pos_and_z.z,
-                illumination,
+        match (self.screen_iter.next(), self.varyings_iter.next()) {
+            (Some(screen), Some(varyings)) => Some(Pixel::new(
+                IVec2::new(screen.x.round() as i32, screen.y.round() as i32),
+                screen.z,
+                screen.w,
+                varyings,
             )),
             _ => None,
         }
     }
 }
 
-impl Interpolate<Pixel> for Pixel {
-    type Output = InterPixels;
+impl<V: Interpolant> Interpolate<Pixel<V>> for Pixel<V> {
+    type Output = InterPixels<V>;
 
-    fn interpolate(&self, rhs: &Pixel, result_size: usize) -> Self::Output {
+    fn interpolate(&self, rhs: &Pixel<V>, result_size: usize) -> Self::Output {
         InterPixels {
-            pos_and_z_iter: self.xyz_as_vec3().interpolate(&rhs.xyz_as_vec3(), result_size),
-            illu_iter: self
-                .illumination
-                .interpolate(&rhs.illumination, result_size),
+            screen_iter: self
+                .screen_as_vec4()
+                .interpolate(&rhs.screen_as_vec4(), result_size),
+            varyings_iter: self.varyings.interpolate(&rhs.varyings, result_size),
         }
     }
 }
@@ -114,6 +162,9 @@ mod test {
     #[test]
     fn interpolate() {
         let points: Vec<IVec2> = ivec2(5, 3).interpolate(&ivec2(1, 2), 4).collect();
-        assert_eq!(Vec::<IVec2>::new(), points);
+        assert_eq!(
+            vec![ivec2(5, 3), ivec2(4, 3), ivec2(2, 2), ivec2(1, 2)],
+            points
+        );
     }
 }
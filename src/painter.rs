@@ -1,35 +1,89 @@
-use glam::{IVec2, Vec3};
+use glam::{I64Vec2, IVec2, Vec3};
 
 use crate::{
-    geometry::primitives::{Pixel, Vertices},
-    operations::Interpolate,
-    pixels::PixelBuffer,
-    shaders::PixelShader,
+    geometry::primitives::{Gradients, Pixel, SUBPIXEL_HALF, SUBPIXEL_ONE, ScreenVertex, Vertices},
+    operations::{Interpolant, Interpolate},
+    pixels::{ColorBuffer, PixelBuffer, PixelFormat},
+    shaders::{MultisamplePixelShader, PixelShader},
+    span::{self, SimdLevel, Span},
 };
 
 pub trait PointPainter {
     fn draw_point(&mut self, x: u32, y: u32, color: &Vec3);
+
+    /// Draws `colors[i]` at `(x + i, y)` wherever `passed[i]` is set.
+    fn draw_span(&mut self, x: u32, y: u32, colors: &[Vec3], passed: &[bool]) {
+        for (i, (color, passed)) in colors.iter().zip(passed).enumerate() {
+            if *passed {
+                self.draw_point(x + i as u32, y, color);
+            }
+        }
+    }
 }
 
 impl PointPainter for PixelBuffer<'_> {
     fn draw_point(&mut self, x: u32, y: u32, color: &Vec3) {
-        let bytes_offset = (y * self.height + x) * 4;
-        let bytes_offset = bytes_offset as usize;
-        let rgb_8bits_color = (color * 255f32).clamp(Vec3::ZERO, Vec3::splat(255f32));
-        if let Some(pixel_buf_ref) = self.buf.get_mut(bytes_offset..bytes_offset + 4) {
-            let bgr_8bits_color = {
-                let mut raw_color = rgb_8bits_color.as_u8vec3().to_array();
-                raw_color.reverse();
-                raw_color
-            };
-            // BGR
-            pixel_buf_ref[0..3].copy_from_slice(&bgr_8bits_color);
-            // A
-            pixel_buf_ref[3] = 255;
+        let format = self.format;
+        if let Some(pixel_buf_ref) = self.pixel_mut(x, y) {
+            format.encode(*color, pixel_buf_ref);
+        }
+    }
+
+    fn draw_span(&mut self, x: u32, y: u32, colors: &[Vec3], passed: &[bool]) {
+        const CHUNK: usize = 64;
+        let format = self.format;
+        if !matches!(
+            format,
+            PixelFormat::Rgba8 | PixelFormat::Bgra8 | PixelFormat::Rgb8
+        ) {
+            for (i, (color, passed)) in colors.iter().zip(passed).enumerate() {
+                if *passed {
+                    self.draw_point(x + i as u32, y, color);
+                }
+            }
+            return;
+        }
+        // Quantize whole chunks of channels at once, then scatter the
+        // passing pixels in the buffer channel order.
+        let simd = SimdLevel::detect();
+        let mut channels = [0f32; CHUNK * 3];
+        let mut packed = [0u8; CHUNK * 3];
+        for (chunk, (colors, passed)) in colors.chunks(CHUNK).zip(passed.chunks(CHUNK)).enumerate()
+        {
+            let channels = &mut channels[..colors.len() * 3];
+            for (rgb, color) in channels.chunks_exact_mut(3).zip(colors) {
+                rgb.copy_from_slice(&color.to_array());
+            }
+            span::pack_unorm8(simd, channels, &mut packed);
+            for (i, rgb) in packed.chunks_exact(3).take(colors.len()).enumerate() {
+                if !passed[i] {
+                    continue;
+                }
+                let Some(out) = self.pixel_mut(x + (chunk * CHUNK + i) as u32, y) else {
+                    continue;
+                };
+                let [r, g, b] = [rgb[0], rgb[1], rgb[2]];
+                match format {
+                    PixelFormat::Rgba8 => out.copy_from_slice(&[r, g, b, 255]),
+                    PixelFormat::Bgra8 => out.copy_from_slice(&[b, g, r, 255]),
+                    _ => out.copy_from_slice(&[r, g, b]),
+                }
+            }
         }
     }
 }
 
+impl PointPainter for ColorBuffer {
+    fn draw_point(&mut self, x: u32, y: u32, color: &Vec3) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let idx = self.index(x, y);
+        self.colors[idx] = *color;
+        self.drawn[idx] = true;
+    }
+}
+
 pub trait LinePainter {
     fn draw_line(&mut self, start: &IVec2, end: &IVec2, color: &Vec3);
 }
@@ -67,62 +121,507 @@ impl<LP: LinePainter> PolygonPainter for LP {
     }
 }
 
-pub trait PolygonFiller {
-    fn fill_polygon(&mut self, polygon: impl Vertices<Vertex = Pixel>);
+pub trait PolygonFiller<V> {
+    fn fill_polygon(&mut self, polygon: impl Vertices<Vertex = ScreenVertex<V>>);
 }
 
-impl<PS: PixelShader> PolygonFiller for PS {
-    fn fill_polygon(&mut self, polygon: impl Vertices<Vertex = Pixel>) {
+/// Index of the first pixel whose center is at or after the
+/// fixed-point coordinate `v`.
+fn first_center_at_or_after(v: i32) -> i32 {
+    (v - SUBPIXEL_HALF + SUBPIXEL_ONE - 1).div_euclid(SUBPIXEL_ONE)
+}
+
+impl<PS: PixelShader> PolygonFiller<PS::Varyings> for PS {
+    fn fill_polygon(&mut self, polygon: impl Vertices<Vertex = ScreenVertex<PS::Varyings>>) {
         let polygon_vertices = polygon.vertices();
-        let y_max = polygon_vertices
-            .as_ref()
-            .iter()
-            .max_by_key(|v| v.point.y)
-            .unwrap()
-            .point
-            .y;
-        let y_min = polygon_vertices
-            .as_ref()
-            .iter()
-            .min_by_key(|v| v.point.y)
-            .unwrap()
-            .point
-            .y;
-
-        let mut left_pixels: Vec<Pixel> = (y_min..=y_max)
-            .map(|y| Pixel::new(IVec2::new(i32::MAX, y), 0f32, Vec3::ZERO))
-            .collect();
-        let mut right_pixels: Vec<Pixel> = (y_min..=y_max)
-            .map(|y| Pixel::new(IVec2::new(i32::MIN, y), 0f32, Vec3::ZERO))
-            .collect();
-
-        let viter = polygon_vertices.as_ref().iter();
-        let viter_skip1 = polygon_vertices.as_ref().iter().cycle().skip(1);
-
-        let edge_pixel_iter = viter.zip(viter_skip1).flat_map(|(start, end)| {
-            let pixels = (start.point - end.point).abs().max_element() as usize + 1;
-            start.interpolate(end, pixels)
-        });
-
-        for p in edge_pixel_iter {
-            let i = p.point.y - y_min;
-            let i = i as usize;
-            if left_pixels[i].point.x > p.point.x {
-                left_pixels[i] = p;
+        let polygon_vertices = polygon_vertices.as_ref();
+        if polygon_vertices.is_empty() {
+            return;
+        }
+        self.set_gradients(Gradients::of(polygon_vertices));
+        let (scissor_min, scissor_max) = self.scissor();
+
+        // Rows whose centers lie within [top, bottom), limited to the scissor.
+        let top = polygon_vertices.iter().map(|v| v.point.y).min().unwrap();
+        let bottom = polygon_vertices.iter().map(|v| v.point.y).max().unwrap();
+        let y_min = first_center_at_or_after(top).max(scissor_min.y);
+        let y_max = (first_center_at_or_after(bottom) - 1).min(scissor_max.y - 1);
+        if y_min > y_max {
+            return;
+        }
+
+        // Exact edge crossings of each row center, x kept in fixed point.
+        let rows = (y_max - y_min + 1) as usize;
+        let mut left_edges: Vec<Option<(f32, ScreenVertex<PS::Varyings>)>> = vec![None; rows];
+        let mut right_edges: Vec<Option<(f32, ScreenVertex<PS::Varyings>)>> = vec![None; rows];
+
+        let viter = polygon_vertices.iter();
+        let viter_skip1 = polygon_vertices.iter().cycle().skip(1);
+        for (start, end) in viter.zip(viter_skip1) {
+            if start.point.y == end.point.y {
+                continue;
             }
-            if right_pixels[i].point.x < p.point.x {
-                right_pixels[i] = p;
+            let (upper, lower) = if start.point.y < end.point.y {
+                (start, end)
+            } else {
+                (end, start)
+            };
+            let edge_y_min = first_center_at_or_after(upper.point.y).max(y_min);
+            let edge_y_max = (first_center_at_or_after(lower.point.y) - 1).min(y_max);
+            let height = (lower.point.y - upper.point.y) as f32;
+            for y in edge_y_min..=edge_y_max {
+                let center = y * SUBPIXEL_ONE + SUBPIXEL_HALF;
+                let t = (center - upper.point.y) as f32 / height;
+                let x = upper.point.x as f32 + (lower.point.x - upper.point.x) as f32 * t;
+                let i = (y - y_min) as usize;
+                if left_edges[i].is_none_or(|(left, _)| x < left) {
+                    left_edges[i] = Some((x, upper.lerp(lower, t)));
+                }
+                if right_edges[i].is_none_or(|(right, _)| x > right) {
+                    right_edges[i] = Some((x, upper.lerp(lower, t)));
+                }
             }
         }
 
-        for (start, end) in left_pixels.into_iter().zip(right_pixels.into_iter()) {
-            let pixel_num = end.point.x - start.point.x + 1;
-            for pixel in start.interpolate(&end, pixel_num as usize) {
-                if pixel.point.x < 0 || pixel.point.y < 0 {
-                    continue;
+        for (y, (left, right)) in (y_min..).zip(left_edges.into_iter().zip(right_edges)) {
+            let (Some((left_x, left)), Some((right_x, right))) = (left, right) else {
+                continue;
+            };
+            // Pixels whose centers lie within [left, right).
+            let x_min = first_center_at_or_after(left_x.ceil() as i32).max(scissor_min.x);
+            let x_max =
+                (first_center_at_or_after(right_x.ceil() as i32) - 1).min(scissor_max.x - 1);
+            if x_min > x_max {
+                continue;
+            }
+            let span_width = (right_x - left_x).max(1f32);
+            let at = |x: i32| {
+                let center = (x * SUBPIXEL_ONE + SUBPIXEL_HALF) as f32;
+                left.lerp(&right, (center - left_x) / span_width)
+                    .to_pixel(IVec2::new(x, y))
+            };
+            let pixel_num = x_max - x_min + 1;
+            self.shade_span(&Span::new(&at(x_min), &at(x_max), pixel_num as usize));
+        }
+    }
+}
+
+/// Rasterizes by testing pixel centers against the edge functions of each
+/// triangle over its bounding box.
+///
+/// Pixels exactly on a shared edge go to one triangle only, following the
+/// top-left rule, so meshes tile without cracks or double coverage.
+pub trait EdgeFunctionFiller<V> {
+    fn fill_polygon_edge_function(&mut self, polygon: impl Vertices<Vertex = ScreenVertex<V>>);
+}
+
+/// Twice the signed area of `a`, `b`, `c`, positive when they run
+/// clockwise on screen (y pointing down).
+fn orient(a: I64Vec2, b: I64Vec2, c: I64Vec2) -> i64 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+/// With clockwise triangles, a top edge runs exactly rightwards
+/// and a left edge runs upwards.
+fn is_top_left(from: I64Vec2, to: I64Vec2) -> bool {
+    let edge = to - from;
+    (edge.y == 0 && edge.x > 0) || edge.y < 0
+}
+
+impl<PS: PixelShader> EdgeFunctionFiller<PS::Varyings> for PS {
+    fn fill_polygon_edge_function(
+        &mut self,
+        polygon: impl Vertices<Vertex = ScreenVertex<PS::Varyings>>,
+    ) {
+        let polygon_vertices = polygon.vertices();
+        let polygon_vertices = polygon_vertices.as_ref();
+        let Some((first, rest)) = polygon_vertices.split_first() else {
+            return;
+        };
+        self.set_gradients(Gradients::of(polygon_vertices));
+        for pair in rest.windows(2) {
+            fill_triangle(self, [first, &pair[0], &pair[1]]);
+        }
+    }
+}
+
+/// Edge functions of a clockwise triangle and the pixels it may cover.
+struct TriangleSetup<'v, V> {
+    vertices: [&'v ScreenVertex<V>; 3],
+    /// Edges opposite to each vertex.
+    edges: [(I64Vec2, I64Vec2); 3],
+    /// The top-left rule as a bias: non top-left edges need strictly
+    /// positive values.
+    bias: [i64; 3],
+    area_recip: f32,
+    pixel_min: I64Vec2,
+    pixel_max: I64Vec2,
+}
+
+impl<'v, V: Interpolant> TriangleSetup<'v, V> {
+    /// `reach` widens the covered pixel range for samples placed that far
+    /// (in fixed point) from pixel centers.
+    fn new(
+        vertices: [&'v ScreenVertex<V>; 3],
+        scissor: (IVec2, IVec2),
+        reach: i64,
+    ) -> Option<Self> {
+        let [a, mut b, mut c] = vertices;
+        let (pa, mut pb, mut pc) = (
+            a.point.as_i64vec2(),
+            b.point.as_i64vec2(),
+            c.point.as_i64vec2(),
+        );
+
+        let mut area = orient(pa, pb, pc);
+        if area == 0 {
+            return None;
+        }
+        // Culling already happened, just bring both windings to clockwise.
+        if area < 0 {
+            (b, c) = (c, b);
+            (pb, pc) = (pc, pb);
+            area = -area;
+        }
+
+        let one = i64::from(SUBPIXEL_ONE);
+        let half = i64::from(SUBPIXEL_HALF);
+        let (scissor_min, scissor_max) = scissor;
+        let bbox_min = pa.min(pb).min(pc) - reach;
+        let bbox_max = pa.max(pb).max(pc) + reach;
+        // First and last pixel whose center lies within the bounding box.
+        let pixel_min = (bbox_min - half + one - 1)
+            .div_euclid(I64Vec2::splat(one))
+            .max(scissor_min.as_i64vec2());
+        let pixel_max = (bbox_max - half)
+            .div_euclid(I64Vec2::splat(one))
+            .min(scissor_max.as_i64vec2() - 1);
+        if pixel_min.cmpgt(pixel_max).any() {
+            return None;
+        }
+
+        let edges = [(pb, pc), (pc, pa), (pa, pb)];
+        Some(Self {
+            vertices: [a, b, c],
+            edges,
+            bias: edges.map(|(from, to)| if is_top_left(from, to) { 0 } else { -1 }),
+            area_recip: (area as f32).recip(),
+            pixel_min,
+            pixel_max,
+        })
+    }
+
+    /// Walks the candidate pixels, handing each one its edge function
+    /// values at the pixel center.
+    fn for_each_pixel(&self, mut f: impl FnMut(IVec2, [i64; 3])) {
+        let one = i64::from(SUBPIXEL_ONE);
+        let step_x = self.edges.map(|(from, to)| -(to.y - from.y) * one);
+        let step_y = self.edges.map(|(from, to)| (to.x - from.x) * one);
+
+        let origin = self.pixel_min * one + i64::from(SUBPIXEL_HALF);
+        let mut row = self.edges.map(|(from, to)| orient(from, to, origin));
+        for y in self.pixel_min.y..=self.pixel_max.y {
+            let mut w = row;
+            for x in self.pixel_min.x..=self.pixel_max.x {
+                f(IVec2::new(x as i32, y as i32), w);
+                for i in 0..3 {
+                    w[i] += step_x[i];
                 }
-                self.pixel_shader(pixel);
             }
+            for i in 0..3 {
+                row[i] += step_y[i];
+            }
+        }
+    }
+
+    fn covers(&self, w: [i64; 3]) -> bool {
+        (0..3).all(|i| w[i] + self.bias[i] >= 0)
+    }
+
+    fn weights(&self, w: [i64; 3]) -> Vec3 {
+        Vec3::new(w[0] as f32, w[1] as f32, w[2] as f32) * self.area_recip
+    }
+
+    fn pixel(&self, point: IVec2, w: [i64; 3]) -> Pixel<V> {
+        let [a, b, c] = self.vertices;
+        ScreenVertex::barycentric(point, self.weights(w), a, b, c)
+    }
+}
+
+fn fill_triangle<PS: PixelShader>(ps: &mut PS, vertices: [&ScreenVertex<PS::Varyings>; 3]) {
+    let Some(setup) = TriangleSetup::new(vertices, ps.scissor(), 0) else {
+        return;
+    };
+    setup.for_each_pixel(|point, w| {
+        if setup.covers(w) {
+            ps.pixel_shader(setup.pixel(point, w));
+        }
+    });
+}
+
+/// Standard 2x, 4x and 8x sample positions, in fixed point relative to
+/// the pixel center.
+pub fn sample_offsets(samples: usize) -> &'static [IVec2] {
+    // In 1/16th of a pixel, scaled to the sub-pixel grid.
+    const S: i32 = SUBPIXEL_ONE / 16;
+    const X1: [IVec2; 1] = [IVec2::ZERO];
+    const X2: [IVec2; 2] = [IVec2::new(4 * S, 4 * S), IVec2::new(-4 * S, -4 * S)];
+    const X4: [IVec2; 4] = [
+        IVec2::new(-2 * S, -6 * S),
+        IVec2::new(6 * S, -2 * S),
+        IVec2::new(-6 * S, 2 * S),
+        IVec2::new(2 * S, 6 * S),
+    ];
+    const X8: [IVec2; 8] = [
+        IVec2::new(S, -3 * S),
+        IVec2::new(-S, 3 * S),
+        IVec2::new(5 * S, S),
+        IVec2::new(-3 * S, -5 * S),
+        IVec2::new(-5 * S, 5 * S),
+        IVec2::new(-7 * S, -S),
+        IVec2::new(3 * S, 7 * S),
+        IVec2::new(7 * S, -7 * S),
+    ];
+    match samples {
+        2 => &X2,
+        4 => &X4,
+        8 => &X8,
+        _ => &X1,
+    }
+}
+
+/// Edge-function rasterization testing several samples per pixel.
+///
+/// Coverage and depth are evaluated per sample while attributes are
+/// evaluated once per pixel, at its center or, when the center falls
+/// outside the triangle, at its first covered sample.
+pub trait MultisampleFiller<V> {
+    fn fill_polygon_multisample(&mut self, polygon: impl Vertices<Vertex = ScreenVertex<V>>);
+}
+
+impl<PS: MultisamplePixelShader> MultisampleFiller<PS::Varyings> for PS {
+    fn fill_polygon_multisample(
+        &mut self,
+        polygon: impl Vertices<Vertex = ScreenVertex<PS::Varyings>>,
+    ) {
+        let polygon_vertices = polygon.vertices();
+        let polygon_vertices = polygon_vertices.as_ref();
+        let Some((first, rest)) = polygon_vertices.split_first() else {
+            return;
+        };
+        self.set_gradients(Gradients::of(polygon_vertices));
+        for pair in rest.windows(2) {
+            fill_triangle_multisample(self, [first, &pair[0], &pair[1]]);
+        }
+    }
+}
+
+fn fill_triangle_multisample<PS: MultisamplePixelShader>(
+    ps: &mut PS,
+    vertices: [&ScreenVertex<PS::Varyings>; 3],
+) {
+    let offsets = sample_offsets(ps.sample_count());
+    let Some(setup) = TriangleSetup::new(vertices, ps.scissor(), i64::from(SUBPIXEL_HALF)) else {
+        return;
+    };
+    // How much each edge function moves between the center and each sample.
+    let sample_deltas = setup.edges.map(|(from, to)| {
+        offsets
+            .iter()
+            .map(|o| -(to.y - from.y) * i64::from(o.x) + (to.x - from.x) * i64::from(o.y))
+            .collect::<Vec<_>>()
+    });
+    let [a, b, c] = setup.vertices;
+    let vertex_depths = Vec3::new(a.depth, b.depth, c.depth);
+
+    let mut depths = vec![0f32; offsets.len()];
+    setup.for_each_pixel(|point, w| {
+        let mut coverage = 0u32;
+        let mut shading_w = setup.covers(w).then_some(w);
+        for (s, depth) in depths.iter_mut().enumerate() {
+            let ws = [0, 1, 2].map(|i| w[i] + sample_deltas[i][s]);
+            if setup.covers(ws) {
+                coverage |= 1 << s;
+                *depth = setup.weights(ws).dot(vertex_depths);
+                shading_w.get_or_insert(ws);
+            }
+        }
+        if let Some(shading_w) = shading_w.filter(|_| coverage != 0) {
+            ps.pixel_shader_multisample(setup.pixel(point, shading_w), coverage, &depths);
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use glam::{IVec2, Vec2, Vec4, ivec2, vec2};
+
+    use super::{EdgeFunctionFiller, MultisampleFiller, PolygonFiller};
+    use crate::{
+        geometry::primitives::{ClipVertex, Pixel, Polygon2D, SUBPIXEL_ONE, Varyings},
+        shaders::{InterpolationMode, MultisamplePixelShader, PixelShader},
+    };
+
+    struct CoverageCounter {
+        size: IVec2,
+        hits: Vec<u32>,
+    }
+
+    impl CoverageCounter {
+        fn new() -> Self {
+            Self {
+                size: ivec2(16, 16),
+                hits: vec![0; 256],
+            }
+        }
+    }
+
+    impl PixelShader for CoverageCounter {
+        type Varyings = Varyings;
+
+        fn pixel_shader(&mut self, p: Pixel) {
+            self.hits[(p.point.y * self.size.x + p.point.x) as usize] += 1;
+        }
+
+        fn scissor(&self) -> (IVec2, IVec2) {
+            (IVec2::ZERO, self.size)
+        }
+    }
+
+    /// Counts covered samples per pixel, four samples each.
+    impl MultisamplePixelShader for CoverageCounter {
+        fn sample_count(&self) -> usize {
+            4
+        }
+
+        fn pixel_shader_multisample(&mut self, p: Pixel, coverage: u32, _depths: &[f32]) {
+            self.hits[(p.point.y * self.size.x + p.point.x) as usize] += coverage.count_ones();
+        }
+    }
+
+    #[test]
+    fn shared_edges_are_covered_once() {
+        // A quad split along its diagonal, with sub-pixel corners.
+        let quad = [ivec2(1, 2), ivec2(13, 1), ivec2(14, 12), ivec2(2, 14)]
+            .map(|p| p * SUBPIXEL_ONE + ivec2(37, 201));
+
+        let mut edge_function = CoverageCounter::new();
+        edge_function
+            .fill_polygon_edge_function(Polygon2D::from_points(&[quad[0], quad[1], quad[2]]));
+        edge_function
+            .fill_polygon_edge_function(Polygon2D::from_points(&[quad[0], quad[2], quad[3]]));
+        assert!(edge_function.hits.iter().all(|hits| *hits <= 1));
+
+        let mut whole = CoverageCounter::new();
+        whole.fill_polygon_edge_function(Polygon2D::from_points(&quad));
+        assert_eq!(whole.hits, edge_function.hits);
+
+        let mut scanline = CoverageCounter::new();
+        scanline.fill_polygon(Polygon2D::from_points(&[quad[0], quad[1], quad[2]]));
+        scanline.fill_polygon(Polygon2D::from_points(&[quad[0], quad[2], quad[3]]));
+        assert_eq!(edge_function.hits, scanline.hits);
+
+        let mut clamped = CoverageCounter::new();
+        clamped.fill_polygon_edge_function(Polygon2D::from_points(
+            &[ivec2(-40, -40), ivec2(80, -40), ivec2(-40, 80)].map(|p| p * SUBPIXEL_ONE),
+        ));
+        assert!(clamped.hits.iter().all(|hits| *hits == 1));
+    }
+
+    #[test]
+    fn sub_pixel_vertices_move_coverage() {
+        // A sliver only reaching the center of the pixel next to it
+        // once its right corners move by a fraction of a pixel.
+        let sliver = |offset: i32| {
+            let mut counter = CoverageCounter::new();
+            counter.fill_polygon_edge_function(Polygon2D::from_points(&[
+                ivec2(0, 0),
+                ivec2(SUBPIXEL_ONE + offset, 0),
+                ivec2(SUBPIXEL_ONE + offset, SUBPIXEL_ONE),
+                ivec2(0, SUBPIXEL_ONE),
+            ]));
+            counter.hits[..2].to_vec()
+        };
+        assert_eq!(vec![1, 0], sliver(SUBPIXEL_ONE / 2));
+        assert_eq!(vec![1, 1], sliver(SUBPIXEL_ONE / 2 + 1));
+    }
+
+    #[test]
+    fn multisample_coverage_splits_shared_edges() {
+        let quad = [ivec2(1, 2), ivec2(13, 1), ivec2(14, 12), ivec2(2, 14)]
+            .map(|p| p * SUBPIXEL_ONE + ivec2(37, 201));
+        let mut counter = CoverageCounter::new();
+        counter.fill_polygon_multisample(Polygon2D::from_points(&[quad[0], quad[1], quad[2]]));
+        counter.fill_polygon_multisample(Polygon2D::from_points(&[quad[0], quad[2], quad[3]]));
+        assert!(counter.hits.iter().all(|samples| *samples <= 4));
+        // Pixels on the quad's border are partially covered.
+        assert!(counter.hits.iter().any(|samples| (1..4).contains(samples)));
+        assert!(counter.hits.contains(&4));
+    }
+
+    /// Keeps the corrected value of a scalar varying at one pixel.
+    struct Probe {
+        point: IVec2,
+        value: Option<f32>,
+    }
+
+    impl PixelShader for Probe {
+        type Varyings = f32;
+
+        fn pixel_shader(&mut self, p: Pixel<f32>) {
+            if p.point == self.point {
+                self.value = Some(p.corrected_varyings());
+            }
+        }
+
+        fn scissor(&self) -> (IVec2, IVec2) {
+            (IVec2::ZERO, ivec2(16, 16))
+        }
+    }
+
+    #[test]
+    fn interpolation_follows_the_mode() {
+        // Vertices on pixel centers, the far ones four times as deep as
+        // the near one, with a varying going from 0 to 1 away from it.
+        let viewport = vec2(16.0, 16.0);
+        let vertex = |pixel: Vec2, w: f32, varying: f32| {
+            let ndc = pixel / viewport * 2.0 - 1.0;
+            ClipVertex::new(Vec4::new(ndc.x * w, ndc.y * w, 0.5 * w, w), varying)
+        };
+        let clip = [
+            vertex(vec2(1.5, 1.5), 1.0, 0.0),
+            vertex(vec2(13.5, 1.5), 4.0, 1.0),
+            vertex(vec2(1.5, 13.5), 4.0, 1.0),
+        ];
+        // Pixel (4, 4) is halfway between the near vertex and the middle
+        // of the far edge.
+        let probe = |interpolation, edge_function: bool| {
+            let polygon = Polygon2D {
+                vertices: clip
+                    .iter()
+                    .map(|v| v.project(viewport, interpolation))
+                    .collect(),
+            };
+            let mut probe = Probe {
+                point: ivec2(4, 4),
+                value: None,
+            };
+            if edge_function {
+                probe.fill_polygon_edge_function(polygon);
+            } else {
+                probe.fill_polygon(polygon);
+            }
+            probe.value.unwrap()
+        };
+        // (0.5 * 0 / 1 + 0.5 * 1 / 4) / (0.5 / 1 + 0.5 / 4)
+        let perspective = 0.2;
+        let affine = 0.5;
+        for edge_function in [false, true] {
+            let value = probe(InterpolationMode::Perspective, edge_function);
+            assert!((value - perspective).abs() < 1e-5, "{value}");
+            assert!((value - affine).abs() > 0.1);
+            let value = probe(InterpolationMode::Affine, edge_function);
+            assert!((value - affine).abs() < 1e-5, "{value}");
         }
     }
 }
@@ -1,14 +1,246 @@
+use glam::Vec3;
+
+use crate::error::{Error, Result};
+
+/// Memory layout of a single pixel in the host's buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    Rgba8,
+    Bgra8,
+    Rgb8,
+    /// 16 bits little endian, red in the high bits.
+    Rgb565,
+    Gray8,
+    /// Four native endian `f32`, not clamped.
+    Rgba32F,
+}
+
+impl TryFrom<u32> for PixelFormat {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self> {
+        Ok(match value {
+            0 => PixelFormat::Rgba8,
+            1 => PixelFormat::Bgra8,
+            2 => PixelFormat::Rgb8,
+            3 => PixelFormat::Rgb565,
+            4 => PixelFormat::Gray8,
+            5 => PixelFormat::Rgba32F,
+            _ => {
+                return Err(Error::invalid_argument(format!(
+                    "unknown pixel format {value}"
+                )));
+            }
+        })
+    }
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgba8 | PixelFormat::Bgra8 => 4,
+            PixelFormat::Rgb8 => 3,
+            PixelFormat::Rgb565 => 2,
+            PixelFormat::Gray8 => 1,
+            PixelFormat::Rgba32F => 16,
+        }
+    }
+
+    /// Writes `color` (linear, nominally in `[0, 1]`) into `out`,
+    /// which must be exactly `bytes_per_pixel` long.
+    pub fn encode(self, color: Vec3, out: &mut [u8]) {
+        let to_u8 = |c: Vec3| {
+            (c * 255f32)
+                .clamp(Vec3::ZERO, Vec3::splat(255f32))
+                .as_u8vec3()
+        };
+        match self {
+            PixelFormat::Rgba8 => {
+                let [r, g, b] = to_u8(color).to_array();
+                out.copy_from_slice(&[r, g, b, 255]);
+            }
+            PixelFormat::Bgra8 => {
+                let [r, g, b] = to_u8(color).to_array();
+                out.copy_from_slice(&[b, g, r, 255]);
+            }
+            PixelFormat::Rgb8 => out.copy_from_slice(&to_u8(color).to_array()),
+            PixelFormat::Rgb565 => {
+                let c = (color.clamp(Vec3::ZERO, Vec3::ONE) * Vec3::new(31f32, 63f32, 31f32))
+                    .round()
+                    .as_uvec3();
+                let packed = ((c.x << 11) | (c.y << 5) | c.z) as u16;
+                out.copy_from_slice(&packed.to_le_bytes());
+            }
+            PixelFormat::Gray8 => {
+                let luma = color.dot(Vec3::new(0.2126, 0.7152, 0.0722));
+                out[0] = (luma * 255f32).clamp(0f32, 255f32) as u8;
+            }
+            PixelFormat::Rgba32F => {
+                for (chunk, c) in out.chunks_exact_mut(4).zip(color.extend(1f32).to_array()) {
+                    chunk.copy_from_slice(&c.to_ne_bytes());
+                }
+            }
+        }
+    }
+}
+
 pub struct PixelBuffer<'b> {
+    pub width: u32,
     pub height: u32,
+    /// Distance in bytes between the starts of two consecutive rows.
+    pub pitch: usize,
+    pub format: PixelFormat,
     pub buf: &'b mut [u8],
 }
 
 impl<'b> PixelBuffer<'b> {
-    pub fn new(height: u32, buf: &'b mut [u8]) -> PixelBuffer<'b> {
-        Self { height, buf }
+    pub fn new(
+        width: u32,
+        height: u32,
+        pitch: usize,
+        format: PixelFormat,
+        buf: &'b mut [u8],
+    ) -> Result<PixelBuffer<'b>> {
+        let row_len = width as usize * format.bytes_per_pixel();
+        if pitch < row_len {
+            return Err(Error::invalid_argument(format!(
+                "row pitch {pitch} is smaller than a {width} pixels row ({row_len} bytes)"
+            )));
+        }
+        let required = Self::required_len(width, height, pitch, format)?;
+        if buf.len() < required {
+            return Err(Error::invalid_argument(format!(
+                "pixel buffer holds {} bytes, {required} required",
+                buf.len()
+            )));
+        }
+        Ok(Self {
+            width,
+            height,
+            pitch,
+            format,
+            buf,
+        })
     }
 
+    /// Smallest buffer covering `height` rows, the last one not padded.
+    pub fn required_len(
+        width: u32,
+        height: u32,
+        pitch: usize,
+        format: PixelFormat,
+    ) -> Result<usize> {
+        match height {
+            0 => Some(0),
+            _ => pitch
+                .checked_mul(height as usize - 1)
+                .and_then(|len| len.checked_add(width as usize * format.bytes_per_pixel())),
+        }
+        // Slices cannot be any larger.
+        .filter(|&len| len <= isize::MAX as usize)
+        .ok_or_else(|| {
+            Error::invalid_argument(format!(
+                "{height} rows of pitch {pitch} overflow the address space"
+            ))
+        })
+    }
+
+    /// Fills the pixels of every row with `val`, leaving row padding untouched.
     pub fn memset(&mut self, val: u8) {
-        self.buf.fill(val);
+        let row_len = self.width as usize * self.format.bytes_per_pixel();
+        for y in 0..self.height as usize {
+            let offset = y * self.pitch;
+            self.buf[offset..offset + row_len].fill(val);
+        }
+    }
+
+    pub fn pixel_mut(&mut self, x: u32, y: u32) -> Option<&mut [u8]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let bpp = self.format.bytes_per_pixel();
+        let offset = y as usize * self.pitch + x as usize * bpp;
+        self.buf.get_mut(offset..offset + bpp)
+    }
+}
+
+/// Linear float color image used as an intermediate render target.
+pub struct ColorBuffer {
+    pub width: u32,
+    pub height: u32,
+    pub colors: Vec<Vec3>,
+    /// Whether anything was drawn to each pixel, so the background of the
+    /// final target can be left alone.
+    pub drawn: Vec<bool>,
+}
+
+impl ColorBuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        let pixel_num = (width * height) as usize;
+        Self {
+            width,
+            height,
+            colors: vec![Vec3::ZERO; pixel_num],
+            drawn: vec![false; pixel_num],
+        }
+    }
+
+    pub fn index(&self, x: u32, y: u32) -> usize {
+        (y * self.width + x) as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glam::Vec3;
+
+    use super::{PixelBuffer, PixelFormat};
+
+    fn encode(format: PixelFormat, color: Vec3) -> Vec<u8> {
+        let mut out = vec![0u8; format.bytes_per_pixel()];
+        format.encode(color, &mut out);
+        out
+    }
+
+    #[test]
+    fn encode_formats() {
+        let color = Vec3::new(1f32, 0.5, 0f32);
+        assert_eq!(vec![255, 127, 0, 255], encode(PixelFormat::Rgba8, color));
+        assert_eq!(vec![0, 127, 255, 255], encode(PixelFormat::Bgra8, color));
+        assert_eq!(vec![255, 127, 0], encode(PixelFormat::Rgb8, color));
+        assert_eq!(
+            ((31u16 << 11) | (32 << 5)).to_le_bytes().to_vec(),
+            encode(PixelFormat::Rgb565, color)
+        );
+        assert_eq!(vec![255], encode(PixelFormat::Gray8, Vec3::ONE));
+        assert_eq!(
+            [1f32, 0.5, 0f32, 1f32]
+                .iter()
+                .flat_map(|c| c.to_ne_bytes())
+                .collect::<Vec<_>>(),
+            encode(PixelFormat::Rgba32F, color)
+        );
+    }
+
+    #[test]
+    fn pitched_rows() {
+        let mut raw = vec![7u8; PixelBuffer::required_len(2, 2, 8, PixelFormat::Rgb8).unwrap()];
+        assert_eq!(14, raw.len());
+        let mut buf = PixelBuffer::new(2, 2, 8, PixelFormat::Rgb8, &mut raw).unwrap();
+        buf.memset(0);
+        PixelFormat::Rgb8.encode(Vec3::ONE, buf.pixel_mut(1, 1).unwrap());
+        assert!(buf.pixel_mut(2, 1).is_none());
+        assert_eq!(vec![0, 0, 0, 0, 0, 0, 7, 7, 0, 0, 0, 255, 255, 255], raw);
+    }
+
+    #[test]
+    fn overflowing_pitch() {
+        let pitch = usize::MAX / 2 + 1;
+        assert!(PixelBuffer::required_len(2, 3, pitch, PixelFormat::Rgb8).is_err());
+        // A single row never steps by the pitch.
+        let one_row = PixelBuffer::required_len(2, 1, pitch, PixelFormat::Rgb8);
+        assert_eq!(6, one_row.unwrap());
+        // Fits in a usize, but not in a slice.
+        assert!(PixelBuffer::required_len(2, 2, isize::MAX as usize, PixelFormat::Rgb8).is_err());
     }
 }
@@ -0,0 +1,182 @@
+use std::f32::consts::PI;
+
+use glam::Vec3;
+
+use crate::{
+    error::{Error, Result},
+    painter::PointPainter,
+    pixels::ColorBuffer,
+};
+
+/// Reconstruction filter used when shrinking a supersampled image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownsampleFilter {
+    /// Plain average of the covered source pixels.
+    Box,
+    /// Triangle filter one target pixel wide on each side.
+    Tent,
+    /// Three lobe Lanczos, sharpest, may ring on hard edges.
+    Lanczos3,
+}
+
+impl TryFrom<u32> for DownsampleFilter {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self> {
+        Ok(match value {
+            0 => DownsampleFilter::Box,
+            1 => DownsampleFilter::Tent,
+            2 => DownsampleFilter::Lanczos3,
+            _ => {
+                return Err(Error::invalid_argument(format!(
+                    "unknown downsample filter {value}"
+                )));
+            }
+        })
+    }
+}
+
+impl DownsampleFilter {
+    /// Support of the kernel, in target pixels.
+    fn radius(self) -> f32 {
+        match self {
+            DownsampleFilter::Box => 0.5,
+            DownsampleFilter::Tent => 1f32,
+            DownsampleFilter::Lanczos3 => 3f32,
+        }
+    }
+
+    /// Kernel value at `x` target pixels from the center.
+    fn weight(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            DownsampleFilter::Box => (x < 0.5) as u32 as f32,
+            DownsampleFilter::Tent => (1f32 - x).max(0f32),
+            DownsampleFilter::Lanczos3 => {
+                let sinc = |x: f32| {
+                    if x < 1e-6 {
+                        1f32
+                    } else {
+                        (PI * x).sin() / (PI * x)
+                    }
+                };
+                if x < 3f32 {
+                    sinc(x) * sinc(x / 3f32)
+                } else {
+                    0f32
+                }
+            }
+        }
+    }
+
+    /// Normalized `(source index, weight)` taps of every target pixel
+    /// along one axis.
+    fn taps(self, target_len: u32, factor: u32) -> Vec<Vec<(usize, f32)>> {
+        let source_len = (target_len * factor) as i64;
+        let reach = (self.radius() * factor as f32).ceil() as i64;
+        (0..target_len)
+            .map(|t| {
+                let center = (t as f32 + 0.5) * factor as f32;
+                let first = center.floor() as i64 - reach;
+                let last = center.floor() as i64 + reach;
+                let mut taps: Vec<(usize, f32)> = (first.max(0)..=last.min(source_len - 1))
+                    .map(|s| {
+                        let distance = (s as f32 + 0.5 - center) / factor as f32;
+                        (s as usize, self.weight(distance))
+                    })
+                    .filter(|(_, weight)| *weight != 0f32)
+                    .collect();
+                let total: f32 = taps.iter().map(|(_, weight)| weight).sum();
+                taps.iter_mut().for_each(|(_, weight)| *weight /= total);
+                taps
+            })
+            .collect()
+    }
+}
+
+/// Shrinks `source` by an integer `factor` on both axes into `target`,
+/// skipping target pixels whose footprint nothing was drawn to.
+pub fn downsample(
+    source: &ColorBuffer,
+    factor: u32,
+    filter: DownsampleFilter,
+    target: &mut impl PointPainter,
+) {
+    let width = source.width / factor;
+    let height = source.height / factor;
+    let x_taps = filter.taps(width, factor);
+    let y_taps = filter.taps(height, factor);
+
+    // Horizontal pass, `width` by `source.height`.
+    let horizontal: Vec<(Vec3, bool)> = (0..source.height)
+        .flat_map(|y| {
+            x_taps.iter().map(move |taps| {
+                taps.iter()
+                    .fold((Vec3::ZERO, false), |(color, drawn), (x, weight)| {
+                        let idx = source.index(*x as u32, y);
+                        (
+                            color + source.colors[idx] * *weight,
+                            drawn || source.drawn[idx],
+                        )
+                    })
+            })
+        })
+        .collect();
+
+    for (y, taps) in y_taps.iter().enumerate() {
+        for x in 0..width as usize {
+            let (color, drawn) =
+                taps.iter()
+                    .fold((Vec3::ZERO, false), |(color, drawn), (source_y, weight)| {
+                        let (c, d) = horizontal[source_y * width as usize + x];
+                        (color + c * *weight, drawn || d)
+                    });
+            if drawn {
+                target.draw_point(x as u32, y as u32, &color.max(Vec3::ZERO));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glam::Vec3;
+
+    use super::{DownsampleFilter, downsample};
+    use crate::{painter::PointPainter, pixels::ColorBuffer};
+
+    #[test]
+    fn filters_preserve_flat_color() {
+        for filter in [
+            DownsampleFilter::Box,
+            DownsampleFilter::Tent,
+            DownsampleFilter::Lanczos3,
+        ] {
+            let mut source = ColorBuffer::new(12, 8);
+            for y in 0..8 {
+                for x in 0..12 {
+                    source.draw_point(x, y, &Vec3::new(0.25, 0.5, 1f32));
+                }
+            }
+            let mut target = ColorBuffer::new(3, 2);
+            downsample(&source, 4, filter, &mut target);
+            assert!(target.drawn.iter().all(|drawn| *drawn));
+            assert!(
+                target
+                    .colors
+                    .iter()
+                    .all(|c| c.abs_diff_eq(Vec3::new(0.25, 0.5, 1f32), 1e-5))
+            );
+        }
+    }
+
+    #[test]
+    fn box_filter_averages_its_block() {
+        let mut source = ColorBuffer::new(4, 2);
+        source.draw_point(0, 0, &Vec3::ONE);
+        let mut target = ColorBuffer::new(2, 1);
+        downsample(&source, 2, DownsampleFilter::Box, &mut target);
+        assert_eq!(vec![Vec3::splat(0.25), Vec3::ZERO], target.colors);
+        assert_eq!(vec![true, false], target.drawn);
+    }
+}
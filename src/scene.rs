@@ -1,2 +1,3 @@
 pub mod colors;
 pub mod cornell;
+pub mod obj;
@@ -1,7 +1,7 @@
 use crate::geometry::primitives::Triangle;
 
 use super::colors::*;
-use glam::Vec3;
+use glam::{Vec2, Vec3};
 
 // Length of Cornell Box side.
 const L: f32 = 555.0;
@@ -79,6 +79,25 @@ pub const TALL_BLOCK: [Triangle; 10] = {
     ]
 };
 
+/// Maps the box on `[0, 1]` UV coordinates, projecting every triangle
+/// along the axis its normal is closest to.
+pub fn planar_uvs(t: Triangle) -> Triangle {
+    // Not `get_normal`, which would cache the normal before scaling.
+    let n = (t.v2 - t.v0).cross(t.v1 - t.v0).abs();
+    let project = |v: Vec3| {
+        let uv = if n.x >= n.y && n.x >= n.z {
+            Vec2::new(v.z, v.y)
+        } else if n.y >= n.z {
+            Vec2::new(v.x, v.z)
+        } else {
+            Vec2::new(v.x, v.y)
+        };
+        uv / L
+    };
+    let uvs = [project(t.v0), project(t.v1), project(t.v2)];
+    t.with_uvs(uvs)
+}
+
 pub fn scale_triangle(mut t: Triangle) -> Triangle {
     t.v0 *= 2f32 / L;
     t.v1 *= 2f32 / L;
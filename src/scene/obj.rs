@@ -0,0 +1,311 @@
+//! Wavefront OBJ meshes with MTL materials.
+//!
+//! Positions, texture coordinates, normals and polygonal faces are read,
+//! each group or object becoming one [`Mesh`]. Materials only contribute
+//! their diffuse color `Kd`, faces without a known material being white.
+//! Other statements are ignored.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use glam::{Vec2, Vec3};
+
+use crate::{
+    error::{Error, Result},
+    geometry::{mesh::Mesh, primitives::Triangle},
+    scene::colors::WHITE,
+};
+
+/// Loads the meshes of an OBJ file, its material libraries being looked
+/// up next to it. Empty groups are skipped.
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<Mesh>> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    parse(&source, |name| {
+        let path = dir.join(name);
+        fs::read_to_string(&path).map_err(|err| Error::io(&path, err))
+    })
+    .map_err(|err| Error::new(err.status, format!("{}: {}", path.display(), err.message)))
+}
+
+/// Parses OBJ source, `read_mtl` returning the source of the material
+/// libraries it references, see [`load`]. Libraries it fails to read are
+/// skipped, since exported files often reference ones not shipped along.
+pub fn parse(source: &str, mut read_mtl: impl FnMut(&str) -> Result<String>) -> Result<Vec<Mesh>> {
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut materials = HashMap::new();
+    let mut color = WHITE;
+    let mut meshes = Vec::new();
+    let mut triangles = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let at_line =
+            |err: Error| Error::new(err.status, format!("line {}: {}", i + 1, err.message));
+        let mut words = statement(line);
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        match keyword {
+            "v" => positions.push(Vec3::from_array(floats(words.take(3)).map_err(at_line)?)),
+            // OBJ textures start at the bottom row, ours at the top one.
+            "vt" => {
+                let (u, v) = match numbers(words).map_err(at_line)?[..] {
+                    [u] => (u, 0f32),
+                    [u, v] | [u, v, _] => (u, v),
+                    ref uvw => {
+                        return Err(at_line(Error::invalid_argument(format!(
+                            "expected 1 to 3 numbers, got {}",
+                            uvw.len()
+                        ))));
+                    }
+                };
+                uvs.push(Vec2::new(u, 1f32 - v));
+            }
+            "vn" => {
+                let normal = Vec3::from_array(floats(words.take(3)).map_err(at_line)?);
+                normals.push(normal.normalize_or_zero());
+            }
+            "f" => {
+                let corners = words
+                    .map(|corner| Corner::parse(corner, &positions, &uvs, &normals))
+                    .collect::<Result<Vec<_>>>()
+                    .map_err(at_line)?;
+                if corners.len() < 3 {
+                    return Err(at_line(Error::invalid_argument(format!(
+                        "faces need at least 3 vertices, got {}",
+                        corners.len()
+                    ))));
+                }
+                // Fan triangulation, only correct for convex faces.
+                for pair in corners[1..].windows(2) {
+                    triangles.push(triangle([corners[0], pair[0], pair[1]], color));
+                }
+            }
+            "g" | "o" if !triangles.is_empty() => {
+                meshes.push(Mesh::new(std::mem::take(&mut triangles)));
+            }
+            "mtllib" => {
+                for name in words {
+                    let Ok(mtl) = read_mtl(name) else {
+                        continue;
+                    };
+                    materials.extend(parse_mtl(&mtl).map_err(|err| {
+                        at_line(Error::new(err.status, format!("{name}: {}", err.message)))
+                    })?);
+                }
+            }
+            "usemtl" => {
+                let name = words.next().unwrap_or_default();
+                color = materials.get(name).copied().unwrap_or(WHITE);
+            }
+            _ => {}
+        }
+    }
+    if !triangles.is_empty() {
+        meshes.push(Mesh::new(triangles));
+    }
+    Ok(meshes)
+}
+
+/// Diffuse colors of the materials of MTL source, by name.
+pub fn parse_mtl(source: &str) -> Result<HashMap<String, Vec3>> {
+    let mut materials = HashMap::new();
+    let mut current = None;
+    for (i, line) in source.lines().enumerate() {
+        let at_line =
+            |err: Error| Error::new(err.status, format!("line {}: {}", i + 1, err.message));
+        let mut words = statement(line);
+        match words.next() {
+            Some("newmtl") => {
+                let name = words.next().unwrap_or_default().to_owned();
+                materials.insert(name.clone(), WHITE);
+                current = Some(name);
+            }
+            Some("Kd") => {
+                let name = current
+                    .as_ref()
+                    .ok_or_else(|| at_line(Error::invalid_argument("Kd outside of a material")))?;
+                materials.insert(
+                    name.clone(),
+                    Vec3::from_array(floats(words.take(3)).map_err(at_line)?),
+                );
+            }
+            _ => {}
+        }
+    }
+    Ok(materials)
+}
+
+/// Words of a line, comments stripped.
+fn statement(line: &str) -> std::str::SplitWhitespace<'_> {
+    line.split('#')
+        .next()
+        .unwrap_or_default()
+        .split_whitespace()
+}
+
+/// Exactly `N` finite numbers.
+fn floats<'a, const N: usize>(words: impl Iterator<Item = &'a str>) -> Result<[f32; N]> {
+    let numbers = numbers(words)?;
+    numbers.as_slice().try_into().map_err(|_| {
+        Error::invalid_argument(format!("expected {N} numbers, got {}", numbers.len()))
+    })
+}
+
+/// Every word as a finite number.
+fn numbers<'a>(words: impl Iterator<Item = &'a str>) -> Result<Vec<f32>> {
+    words
+        .map(|word| {
+            word.parse::<f32>()
+                .ok()
+                .filter(|f| f.is_finite())
+                .ok_or_else(|| Error::invalid_argument(format!("invalid number {word:?}")))
+        })
+        .collect()
+}
+
+/// Attributes of a face vertex.
+#[derive(Clone, Copy)]
+struct Corner {
+    position: Vec3,
+    uv: Vec2,
+    normal: Option<Vec3>,
+}
+
+impl Corner {
+    /// Parses `v`, `v/vt`, `v//vn` or `v/vt/vn` references.
+    fn parse(corner: &str, positions: &[Vec3], uvs: &[Vec2], normals: &[Vec3]) -> Result<Self> {
+        let mut indices = corner.split('/');
+        let position = *lookup(indices.next(), positions, "position")?.ok_or_else(|| {
+            Error::invalid_argument(format!("face vertex {corner:?} has no position"))
+        })?;
+        let uv = lookup(indices.next(), uvs, "texture coordinate")?.copied();
+        let normal = lookup(indices.next(), normals, "normal")?.copied();
+        Ok(Self {
+            position,
+            uv: uv.unwrap_or(Vec2::ZERO),
+            normal,
+        })
+    }
+}
+
+/// Element of a 1-based index, negative ones counting back from the last
+/// element. Missing and empty indices give `None`.
+fn lookup<'a, T>(index: Option<&str>, elements: &'a [T], name: &str) -> Result<Option<&'a T>> {
+    let Some(index) = index.filter(|index| !index.is_empty()) else {
+        return Ok(None);
+    };
+    let element = index.parse::<i64>().ok().and_then(|i| match i {
+        1.. => elements.get(i as usize - 1),
+        ..0 => elements
+            .len()
+            .checked_sub(i.unsigned_abs() as usize)
+            .map(|i| &elements[i]),
+        0 => None,
+    });
+    element.map(Some).ok_or_else(|| {
+        Error::invalid_argument(format!(
+            "invalid {name} index {index}, {} defined so far",
+            elements.len()
+        ))
+    })
+}
+
+fn triangle([a, b, c]: [Corner; 3], color: Vec3) -> Triangle {
+    // OBJ faces are counterclockwise seen from the front, reversing them
+    // gives the clockwise order `Triangle::get_normal` expects.
+    let corners = [a, c, b];
+    let triangle = Triangle::new(a.position, c.position, b.position, color)
+        .with_uvs(corners.map(|corner| corner.uv));
+    match corners.map(|corner| corner.normal) {
+        [Some(n0), Some(n1), Some(n2)] => triangle.with_normals([n0, n1, n2]),
+        _ => triangle,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glam::{Vec2, Vec3};
+
+    use super::parse;
+    use crate::error::{Error, RusterizerStatus};
+
+    const QUADS: &str = "\
+mtllib box.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0
+vt 1 1 0
+vn 0 0 2
+# Unnamed group.
+f 1 2 3 4
+g red
+usemtl red
+f -4/1/1 -3/2/1 -2/2/1 -1/1/1 # trailing comment
+g empty
+g pentagon
+f 1//1 2//1 3//1 4//1 1//1
+";
+
+    fn mtl(name: &str) -> Result<String, Error> {
+        assert_eq!("box.mtl", name);
+        Ok("newmtl red\nKd 1 0 0\n".into())
+    }
+
+    #[test]
+    fn groups_become_meshes() {
+        let meshes = parse(QUADS, mtl).unwrap();
+        let counts: Vec<_> = meshes.iter().map(|mesh| mesh.triangles.len()).collect();
+        assert_eq!(vec![2, 2, 3], counts);
+
+        let plain = &meshes[0].triangles[0];
+        assert_eq!(Vec3::new(0.75, 0.75, 0.75), plain.color);
+        assert_eq!(None, plain.normals);
+        // Counterclockwise in OBJ, facing +z once reversed.
+        assert_eq!(Vec3::Z, plain.get_normal());
+
+        let red = &meshes[1].triangles[1];
+        assert_eq!(Vec3::X, red.color);
+        assert_eq!(Some([Vec3::Z; 3]), red.normals);
+        // Corners 1, 4 and 3, the v axis flipped.
+        assert_eq!([Vec2::Y, Vec2::Y, Vec2::X], red.uvs);
+    }
+
+    #[test]
+    fn invalid_faces_name_their_line() {
+        for (source, message) in [
+            (
+                "v 0 0 0\nf 1 1\n",
+                "line 2: faces need at least 3 vertices, got 2",
+            ),
+            (
+                "v 0 0 0\nf 1 2 1\n",
+                "line 2: invalid position index 2, 1 defined so far",
+            ),
+            ("v 0 0\n", "line 1: expected 3 numbers, got 2"),
+            ("vt 0 1 0 1\n", "line 1: expected 1 to 3 numbers, got 4"),
+        ] {
+            let Err(err) = parse(source, mtl) else {
+                panic!("{source:?} parsed");
+            };
+            assert_eq!(RusterizerStatus::InvalidArgument, err.status);
+            assert_eq!(message, err.message);
+        }
+    }
+
+    #[test]
+    fn missing_materials_are_white() {
+        let meshes = parse(QUADS, |name| {
+            Err(Error::new(
+                RusterizerStatus::Io,
+                format!("{name}: not found"),
+            ))
+        })
+        .unwrap();
+        assert_eq!(Vec3::new(0.75, 0.75, 0.75), meshes[1].triangles[0].color);
+    }
+}
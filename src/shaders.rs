@@ -1,99 +1,510 @@
-use std::f32::consts::PI;
+use std::sync::Arc;
 
-use glam::{IVec2, Vec2, Vec3};
+use glam::{IVec2, Mat4, Vec3};
 
 use crate::{
+    callback::FragmentCallback,
     camera::Camera,
-    geometry::primitives::{Pixel, Vertex},
+    error::{Error, Result},
+    geometry::primitives::{ClipVertex, Gradients, Pixel, Varyings, Vertex},
+    light::Lighting,
+    operations::Interpolant,
     painter::PointPainter,
+    shadow::ShadowMaps,
+    span::{self, SimdLevel, Span, SpanVaryings},
+    texture::Texture,
 };
 
 pub trait VertexShader {
-    fn vertex_shader(&self, v: &Vertex) -> Pixel;
+    /// Attributes handed to the pixel stage.
+    type Varyings: Interpolant;
+
+    fn vertex_shader(&self, v: &Vertex) -> ClipVertex<Self::Varyings>;
 }
 
 pub trait PixelShader {
-    fn pixel_shader(&mut self, p: Pixel);
+    type Varyings: Interpolant;
+
+    fn pixel_shader(&mut self, p: Pixel<Self::Varyings>);
+
+    /// Shades every pixel of `span`, shaders able to do better than one
+    /// [`PixelShader::pixel_shader`] call per pixel override it.
+    fn shade_span(&mut self, span: &Span<Self::Varyings>) {
+        for pixel in span.pixels() {
+            self.pixel_shader(pixel);
+        }
+    }
+
+    /// Called by rasterizers before the pixels of each polygon, shaders
+    /// needing screen-space derivatives keep them.
+    fn set_gradients(&mut self, _gradients: Gradients<Self::Varyings>) {}
+
+    /// Half-open `[min, max)` rectangle of pixels this shader accepts,
+    /// rasterizers must not emit anything outside of it.
+    fn scissor(&self) -> (IVec2, IVec2);
+}
+
+/// Pixel shader keeping several coverage and depth samples per pixel.
+pub trait MultisamplePixelShader: PixelShader {
+    fn sample_count(&self) -> usize;
+
+    /// Shades `p` once for all the samples set in `coverage`,
+    /// `depths[s]` being the depth at sample `s`.
+    fn pixel_shader_multisample(&mut self, p: Pixel<Self::Varyings>, coverage: u32, depths: &[f32]);
 }
 
-const LIGHT_POS: Vec3 = Vec3::new(0f32, -0.5, -0.7);
-const LIGHT_POWER: Vec3 = Vec3::splat(14f32);
-const INDIRECT_LIGHT_POWER_PER_AREA: Vec3 = Vec3::splat(0.5);
+/// Programmable stage of [`PixelShaderImpl`], coloring the fragments that
+/// pass the depth test.
+///
+/// Its varyings come from a [`VertexShader`] with the same `Varyings`,
+/// which may be any [`Interpolant`].
+pub trait FragmentShader {
+    type Varyings: Interpolant;
+
+    fn fragment_shader(&self, fragment: &Fragment<Self::Varyings>) -> Vec3;
+
+    /// Index and texture of the mesh whose polygons are shaded next.
+    fn set_mesh(&mut self, _mesh: usize, _texture: Option<&Arc<Texture>>) {}
+}
+
+/// Input of [`FragmentShader`]s.
+pub struct Fragment<'g, V> {
+    /// Canvas pixel.
+    pub point: IVec2,
+    /// Perspective corrected varyings.
+    pub varyings: V,
+    z_recip: f32,
+    gradients: &'g Gradients<V>,
+}
+
+impl<V: Interpolant> Fragment<'_, V> {
+    /// How much the varyings change from one pixel to the next along x and
+    /// y, e.g. to pick texture levels of detail.
+    pub fn derivatives(&self) -> (V, V) {
+        self.gradients.derivatives(&self.varyings, self.z_recip)
+    }
+}
+
+/// How vertex attributes are interpolated across a polygon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterpolationMode {
+    /// Interpolates `attribute / w` and `1 / w`, then divides per pixel.
+    Perspective,
+    /// Linear in screen space, cheaper but warps under perspective.
+    Affine,
+}
+
+impl TryFrom<u32> for InterpolationMode {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self> {
+        Ok(match value {
+            0 => InterpolationMode::Perspective,
+            1 => InterpolationMode::Affine,
+            _ => {
+                return Err(Error::invalid_argument(format!(
+                    "unknown interpolation mode {value}"
+                )));
+            }
+        })
+    }
+}
+
+/// Where the light model is evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShadingMode {
+    /// Lights vertices and interpolates the resulting colors, without
+    /// shadows.
+    #[default]
+    Gouraud,
+    /// Interpolates positions and normals and lights every pixel, which
+    /// keeps highlights and falloff inside large polygons.
+    Phong,
+}
+
+impl TryFrom<u32> for ShadingMode {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self> {
+        Ok(match value {
+            0 => ShadingMode::Gouraud,
+            1 => ShadingMode::Phong,
+            _ => {
+                return Err(Error::invalid_argument(format!(
+                    "unknown shading mode {value}"
+                )));
+            }
+        })
+    }
+}
+
+/// The built-in fragment shader, finishing Gouraud shading or lighting
+/// every fragment with Phong shading, modulated by the mesh texture.
+#[derive(Clone, Default)]
+pub struct Material {
+    shading: ShadingMode,
+    lighting: Lighting,
+    shadows: Arc<ShadowMaps>,
+    mesh: usize,
+    texture: Option<Arc<Texture>>,
+    callback: Option<FragmentCallback>,
+}
+
+impl Material {
+    /// Must match the mode the vertex shader ran with.
+    pub fn with_shading(mut self, shading: ShadingMode) -> Self {
+        self.shading = shading;
+        self
+    }
+
+    pub fn with_lighting(mut self, lighting: Lighting) -> Self {
+        self.lighting = lighting;
+        self
+    }
+
+    /// Shadow maps of the lighting, only looked up with Phong shading.
+    pub fn with_shadows(mut self, shadows: Arc<ShadowMaps>) -> Self {
+        self.shadows = shadows;
+        self
+    }
+
+    /// Lets `callback` recolor every fragment once shaded.
+    pub fn with_callback(mut self, callback: Option<FragmentCallback>) -> Self {
+        self.callback = callback;
+        self
+    }
+}
+
+impl FragmentShader for Material {
+    type Varyings = Varyings;
+
+    fn fragment_shader(&self, fragment: &Fragment<Varyings>) -> Vec3 {
+        let varyings = &fragment.varyings;
+        let albedo = self.texture.as_ref().map_or(Vec3::ONE, |texture| {
+            let (dx, dy) = fragment.derivatives();
+            texture.sample(varyings.uv, dx.uv, dy.uv)
+        });
+        let color = match self.shading {
+            ShadingMode::Gouraud => varyings.color * albedo,
+            ShadingMode::Phong => {
+                let normal = varyings.normal.normalize_or_zero();
+                let reflectance = varyings.color * albedo;
+                self.lighting
+                    .illuminate_with(varyings.position, normal, reflectance, |i| {
+                        self.shadows.visibility(i, varyings.position, normal)
+                    })
+            }
+        };
+        match &self.callback {
+            Some(callback) => callback.call(fragment.point, self.mesh, varyings, color),
+            None => color,
+        }
+    }
+
+    fn set_mesh(&mut self, mesh: usize, texture: Option<&Arc<Texture>>) {
+        self.mesh = mesh;
+        // Polygons of a mesh come in a row, skip needless reference counting.
+        if self.texture.as_ref().map(Arc::as_ptr) != texture.map(Arc::as_ptr) {
+            self.texture = texture.cloned();
+        }
+    }
+}
 
-pub struct PixelShaderImpl<'pp, PP> {
+/// Depth tests fragments, colors them with a [`FragmentShader`] and draws
+/// them to a point painter.
+pub struct PixelShaderImpl<'pp, PP, FS: FragmentShader = Material> {
+    /// Canvas position of the painter's top-left pixel, non-zero when
+    /// shading a single tile of the canvas.
+    origin: IVec2,
     width: u32,
     height: u32,
     point_painter: &'pp mut PP,
+    /// One depth per sample, samples of a pixel being contiguous.
     z_buf: Vec<f32>,
+    samples: usize,
+    /// Per sample colors, only used when multisampling.
+    sample_colors: Vec<Vec3>,
+    fragment_shader: FS,
+    /// Of the polygon being filled.
+    gradients: Gradients<FS::Varyings>,
+    simd: SimdLevel,
+    /// Scratch outputs of [`span::shade_span`] and their colors.
+    span_varyings: SpanVaryings,
+    span_passed: Vec<bool>,
+    span_colors: Vec<Vec3>,
 }
 
 impl<'pp, PP: PointPainter> PixelShaderImpl<'pp, PP> {
     pub fn from_point_painter(pp: &'pp mut PP, height: u32, width: u32) -> Self {
+        Self::multisampled(pp, height, width, 1)
+    }
+
+    /// A shader keeping `samples` depth and color samples per pixel,
+    /// drawn to the point painter by [`PixelShaderImpl::resolve`].
+    pub fn multisampled(pp: &'pp mut PP, height: u32, width: u32, samples: usize) -> Self {
+        Self::for_tile(pp, IVec2::ZERO, height, width, samples)
+    }
+
+    /// A shader only accepting the `width` by `height` pixels starting at
+    /// `origin`, drawing them to `pp` relative to that origin.
+    pub fn for_tile(
+        pp: &'pp mut PP,
+        origin: IVec2,
+        height: u32,
+        width: u32,
+        samples: usize,
+    ) -> Self {
+        let pixel_num = (height * width) as usize;
         Self {
+            origin,
             width,
             height,
             point_painter: pp,
-            z_buf: vec![0f32; (height * width) as usize],
+            z_buf: vec![1f32; pixel_num * samples],
+            samples,
+            sample_colors: if samples > 1 {
+                vec![Vec3::ZERO; pixel_num * samples]
+            } else {
+                Vec::new()
+            },
+            fragment_shader: Material::default(),
+            gradients: Gradients::default(),
+            simd: SimdLevel::Scalar,
+            span_varyings: SpanVaryings::default(),
+            span_passed: Vec::new(),
+            span_colors: Vec::new(),
+        }
+    }
+}
+
+impl<'pp, PP: PointPainter, FS: FragmentShader> PixelShaderImpl<'pp, PP, FS> {
+    /// Replaces the fragment shader, along with the varyings it consumes.
+    pub fn with_fragment_shader<F: FragmentShader>(self, fs: F) -> PixelShaderImpl<'pp, PP, F> {
+        PixelShaderImpl {
+            origin: self.origin,
+            width: self.width,
+            height: self.height,
+            point_painter: self.point_painter,
+            z_buf: self.z_buf,
+            samples: self.samples,
+            sample_colors: self.sample_colors,
+            fragment_shader: fs,
+            gradients: Gradients::default(),
+            simd: self.simd,
+            span_varyings: self.span_varyings,
+            span_passed: self.span_passed,
+            span_colors: self.span_colors,
         }
     }
 
+    /// Instruction set of the span kernels, scalar by default. Shading
+    /// panics if the CPU lacks it, see [`SimdLevel::supported`].
+    pub fn with_simd_level(mut self, level: SimdLevel) -> Self {
+        self.simd = level;
+        self
+    }
+
+    /// Mesh of the polygons filled from now on, see
+    /// [`FragmentShader::set_mesh`].
+    pub fn set_mesh(&mut self, mesh: usize, texture: Option<&Arc<Texture>>) {
+        self.fragment_shader.set_mesh(mesh, texture);
+    }
+
+    /// The depth of every sample, consuming the shader.
+    pub fn into_depth_buffer(self) -> Vec<f32> {
+        self.z_buf
+    }
+
+    /// Color of the fragment at `point` from its perspective corrected
+    /// `varyings`.
+    fn fragment_color(&self, point: IVec2, varyings: FS::Varyings, z_recip: f32) -> Vec3 {
+        self.fragment_shader.fragment_shader(&Fragment {
+            point,
+            varyings,
+            z_recip,
+            gradients: &self.gradients,
+        })
+    }
+
     fn get_z_value_idx(&self, point: IVec2) -> usize {
-        (self.width * (point.y as u32) + (point.x as u32)) as usize
+        let local = point - self.origin;
+        (self.width * (local.y as u32) + (local.x as u32)) as usize
+    }
+
+    /// Averages the samples of every pixel something was drawn to.
+    pub fn resolve(&mut self) {
+        if self.samples <= 1 {
+            return;
+        }
+        let samples = self.samples;
+        let pixels = self
+            .z_buf
+            .chunks_exact(samples)
+            .zip(self.sample_colors.chunks_exact(samples))
+            .enumerate();
+        for (idx, (depths, colors)) in pixels {
+            // Untouched pixels keep the cleared background.
+            if depths.iter().all(|depth| *depth >= 1f32) {
+                continue;
+            }
+            let color = colors.iter().sum::<Vec3>() / samples as f32;
+            let idx = idx as u32;
+            self.point_painter
+                .draw_point(idx % self.width, idx / self.width, &color);
+        }
     }
 }
 
-impl<PP: PointPainter> PixelShader for PixelShaderImpl<'_, PP> {
-    fn pixel_shader(&mut self, pixel: Pixel) {
-        let z_idx = self.get_z_value_idx(pixel.point);
+impl<PP: PointPainter, FS: FragmentShader> PixelShader for PixelShaderImpl<'_, PP, FS> {
+    type Varyings = FS::Varyings;
 
-        // FIXME: weird out of bound issue
-        if z_idx >= (self.width * self.height) as usize {
+    fn pixel_shader(&mut self, pixel: Pixel<FS::Varyings>) {
+        let (scissor_min, scissor_max) = self.scissor();
+        debug_assert!(
+            pixel.point.cmpge(scissor_min).all() && pixel.point.cmplt(scissor_max).all(),
+            "pixel {} outside of the scissor rectangle",
+            pixel.point
+        );
+        let z_idx = self.get_z_value_idx(pixel.point);
+        // Fragments in front of the near plane are dropped here, the cleared
+        // z-buffer already rejects those beyond the far plane.
+        if pixel.depth < 0f32 {
             return;
         }
-        let z_recip = self.z_buf[z_idx];
-        if pixel.z_recip > z_recip {
-            self.z_buf[z_idx] = pixel.z_recip;
-            self.point_painter.draw_point(
-                pixel.point.x as u32,
-                pixel.point.y as u32,
-                &pixel.illumination,
-            );
+        let depth = self.z_buf[z_idx];
+        if pixel.depth < depth {
+            self.z_buf[z_idx] = pixel.depth;
+            let local = pixel.point - self.origin;
+            let color = self.fragment_color(pixel.point, pixel.corrected_varyings(), pixel.z_recip);
+            self.point_painter
+                .draw_point(local.x as u32, local.y as u32, &color);
         }
     }
-}
 
-pub struct VertexShaderImpl<'c> {
-    camera: &'c Camera,
+    fn shade_span(&mut self, span: &Span<FS::Varyings>) {
+        // Samples of a row are not contiguous when multisampling.
+        if self.samples > 1 {
+            for pixel in span.pixels() {
+                self.pixel_shader(pixel);
+            }
+            return;
+        }
+        let start = self.get_z_value_idx(span.point);
+        self.span_passed.resize(span.len, false);
+        span::shade_span(
+            self.simd,
+            span,
+            &mut self.z_buf[start..start + span.len],
+            &mut self.span_varyings,
+            &mut self.span_passed,
+        );
+        // Failing pixels hold stale varyings and are never drawn, nor shaded
+        // since fragment shaders may have side effects.
+        let mut colors = std::mem::take(&mut self.span_colors);
+        colors.clear();
+        colors.extend(
+            self.span_passed[..span.len]
+                .iter()
+                .enumerate()
+                .map(|(i, &passed)| {
+                    if !passed {
+                        return Vec3::ZERO;
+                    }
+                    let point = span.point + IVec2::new(i as i32, 0);
+                    let z_recip = span.z_recip + span.z_recip_step * i as f32;
+                    self.fragment_color(point, self.span_varyings.get(i), z_recip)
+                }),
+        );
+        self.span_colors = colors;
+        let local = span.point - self.origin;
+        self.point_painter.draw_span(
+            local.x as u32,
+            local.y as u32,
+            &self.span_colors[..span.len],
+            &self.span_passed[..span.len],
+        );
+    }
+
+    fn set_gradients(&mut self, gradients: Gradients<FS::Varyings>) {
+        self.gradients = gradients;
+    }
+
+    fn scissor(&self) -> (IVec2, IVec2) {
+        (
+            self.origin,
+            self.origin + IVec2::new(self.width as i32, self.height as i32),
+        )
+    }
 }
 
-impl<'c> VertexShaderImpl<'c> {
-    pub fn wrap_camera(camera: &'c Camera) -> Self {
-        VertexShaderImpl { camera }
+impl<PP: PointPainter, FS: FragmentShader> MultisamplePixelShader for PixelShaderImpl<'_, PP, FS> {
+    fn sample_count(&self) -> usize {
+        self.samples
+    }
+
+    fn pixel_shader_multisample(
+        &mut self,
+        pixel: Pixel<FS::Varyings>,
+        coverage: u32,
+        depths: &[f32],
+    ) {
+        let first_sample = self.get_z_value_idx(pixel.point) * self.samples;
+        let mut color = None;
+        for (s, depth) in depths.iter().enumerate() {
+            let idx = first_sample + s;
+            if coverage & (1 << s) == 0 || *depth < 0f32 || *depth >= self.z_buf[idx] {
+                continue;
+            }
+            self.z_buf[idx] = *depth;
+            self.sample_colors[idx] = *color.get_or_insert_with(|| {
+                self.fragment_color(pixel.point, pixel.corrected_varyings(), pixel.z_recip)
+            });
+        }
     }
 }
 
-impl VertexShader for VertexShaderImpl<'_> {
-    fn vertex_shader(&self, vertex: &Vertex) -> Pixel {
-        let v = self.camera.rotation * (vertex.point - self.camera.position);
+pub struct VertexShaderImpl {
+    view_projection: Mat4,
+    shading: ShadingMode,
+    lighting: Lighting,
+}
 
-        let focal = self.camera.focal as f32;
-        let width = self.camera.width as f32;
-        let height = self.camera.height as f32;
+impl VertexShaderImpl {
+    pub fn wrap_camera(camera: &Camera) -> Self {
+        VertexShaderImpl {
+            view_projection: camera.view_projection_matrix(),
+            shading: ShadingMode::Gouraud,
+            lighting: Lighting::default(),
+        }
+    }
 
-        let projected_point =
-            (focal / v.z) * Vec2::new(v.x, v.y) + Vec2::new(width / 2f32, height / 2f32);
+    pub fn with_shading(mut self, shading: ShadingMode) -> Self {
+        self.shading = shading;
+        self
+    }
 
-        // illumination calculation
-        let r = LIGHT_POS - vertex.point;
-        let n = vertex.normal;
-        let d = (r.normalize().dot(n)).max(0f32) / (4f32 * PI * r.dot(r)) * LIGHT_POWER;
+    pub fn with_lighting(mut self, lighting: Lighting) -> Self {
+        self.lighting = lighting;
+        self
+    }
+}
 
-        let illumination = vertex.reflectance * (d + INDIRECT_LIGHT_POWER_PER_AREA);
+impl VertexShader for VertexShaderImpl {
+    type Varyings = Varyings;
 
-        Pixel::new(
-            projected_point.as_ivec2(),
-            (self.camera.position.z - v.z).abs().recip(),
-            illumination,
+    fn vertex_shader(&self, vertex: &Vertex) -> ClipVertex {
+        let clip = self.view_projection * vertex.point.extend(1f32);
+        // Phong shading leaves the light model to the pixel shader.
+        let color = match self.shading {
+            ShadingMode::Gouraud => {
+                self.lighting
+                    .illuminate(vertex.point, vertex.normal, vertex.reflectance)
+            }
+            ShadingMode::Phong => vertex.reflectance,
+        };
+        ClipVertex::new(
+            clip,
+            Varyings::new(color, vertex.point, vertex.normal, vertex.uv),
         )
     }
 }
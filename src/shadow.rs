@@ -0,0 +1,369 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use glam::{IVec2, Mat4, Vec2, Vec3};
+
+use crate::{
+    error::{Error, Result},
+    geometry::{
+        mesh::Mesh,
+        primitives::{ClipVertex, Vertex},
+    },
+    light::{Light, LightKind, Lighting},
+    painter::{PointPainter, PolygonFiller},
+    shaders::{Fragment, FragmentShader, InterpolationMode, PixelShaderImpl, VertexShader},
+};
+
+/// Largest supported shadow map side, in texels.
+pub const MAX_SHADOW_MAP_SIZE: u32 = 4096;
+
+/// Largest supported PCF kernel radius, in texels.
+pub const MAX_PCF_RADIUS: u32 = 4;
+
+/// Stored depths a surface may exceed before it counts as shadowed.
+const DEPTH_BIAS: f32 = 1e-4;
+
+/// Surfaces are looked up this many texels away along their normal, which
+/// keeps them from shadowing themselves.
+const NORMAL_OFFSET_TEXELS: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShadowSettings {
+    /// Side of every shadow map, cube maps have six of them.
+    pub map_size: u32,
+    /// Percentage closer filtering averages `(2 * radius + 1)^2` depth tests.
+    pub pcf_radius: u32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            map_size: 512,
+            pcf_radius: 1,
+        }
+    }
+}
+
+impl ShadowSettings {
+    pub fn new(map_size: u32, pcf_radius: u32) -> Result<Self> {
+        if !(1..=MAX_SHADOW_MAP_SIZE).contains(&map_size) {
+            return Err(Error::invalid_argument(format!(
+                "shadow map size must be within 1..={MAX_SHADOW_MAP_SIZE}, got {map_size}"
+            )));
+        }
+        Ok(Self {
+            map_size,
+            pcf_radius: check_pcf_radius(pcf_radius)?,
+        })
+    }
+}
+
+/// Fails with radii above [`MAX_PCF_RADIUS`].
+pub fn check_pcf_radius(pcf_radius: u32) -> Result<u32> {
+    if pcf_radius > MAX_PCF_RADIUS {
+        return Err(Error::invalid_argument(format!(
+            "PCF radius must be at most {MAX_PCF_RADIUS}, got {pcf_radius}"
+        )));
+    }
+    Ok(pcf_radius)
+}
+
+/// Shadow maps of every light, indexed like [`Lighting::lights`].
+#[derive(Default)]
+pub struct ShadowMaps {
+    shadows: Vec<Option<Shadow>>,
+    pcf_radius: u32,
+}
+
+impl ShadowMaps {
+    /// Depth pre-pass of `meshes` from every enabled light.
+    pub fn render(lighting: &Lighting, meshes: &[Mesh], settings: ShadowSettings) -> Self {
+        let bounds = Bounds::of(meshes);
+        Self {
+            shadows: lighting
+                .lights()
+                .iter()
+                .map(|light| {
+                    bounds
+                        .filter(|_| light.is_enabled())
+                        .map(|bounds| Shadow::render(light, bounds, settings.map_size, meshes))
+                })
+                .collect(),
+            pcf_radius: settings.pcf_radius,
+        }
+    }
+
+    /// Fraction of light `index` reaching a surface point with unit `normal`.
+    pub fn visibility(&self, index: usize, position: Vec3, normal: Vec3) -> f32 {
+        match self.shadows.get(index) {
+            Some(Some(shadow)) => shadow.visibility(position, normal, self.pcf_radius),
+            _ => 1f32,
+        }
+    }
+}
+
+/// Shadow maps of a single light.
+enum Shadow {
+    Single(ShadowMap),
+    /// One map per cube face around a point light, in `+x, -x, +y, -y, +z,
+    /// -z` order.
+    Cube {
+        center: Vec3,
+        faces: Box<[ShadowMap; 6]>,
+    },
+}
+
+impl Shadow {
+    fn render(light: &Light, bounds: Bounds, size: u32, meshes: &[Mesh]) -> Self {
+        // Far enough to see the whole scene from wherever the light is.
+        let far = (light.position() - bounds.center).length() + bounds.radius;
+        let near = far * 1e-3;
+        let texel = 2f32 * bounds.radius / size as f32;
+        let normal_offset = NORMAL_OFFSET_TEXELS * texel;
+        match light.kind() {
+            LightKind::Directional => {
+                let direction = light.direction();
+                let eye = bounds.center - direction * 2f32 * bounds.radius;
+                let r = bounds.radius;
+                let projection = Mat4::orthographic_lh(-r, r, -r, r, 0f32, 4f32 * r);
+                let view = Mat4::look_to_lh(eye, direction, up_for(direction));
+                Shadow::Single(ShadowMap::render(
+                    projection * view,
+                    size,
+                    normal_offset,
+                    meshes,
+                ))
+            }
+            LightKind::Spot => {
+                // Twice the outer cone half angle, kept away from 180 degrees.
+                let fov = (2f32 * light.cone().1).clamp(PI / 180f32, PI * 17f32 / 18f32);
+                let direction = light.direction();
+                let projection = Mat4::perspective_lh(fov, 1f32, near, far);
+                let view = Mat4::look_to_lh(light.position(), direction, up_for(direction));
+                Shadow::Single(ShadowMap::render(
+                    projection * view,
+                    size,
+                    normal_offset,
+                    meshes,
+                ))
+            }
+            LightKind::Point => {
+                let projection = Mat4::perspective_lh(FRAC_PI_2, 1f32, near, far);
+                let faces = [
+                    Vec3::X,
+                    Vec3::NEG_X,
+                    Vec3::Y,
+                    Vec3::NEG_Y,
+                    Vec3::Z,
+                    Vec3::NEG_Z,
+                ]
+                .map(|direction| {
+                    let view = Mat4::look_to_lh(light.position(), direction, up_for(direction));
+                    ShadowMap::render(projection * view, size, normal_offset, meshes)
+                });
+                Shadow::Cube {
+                    center: light.position(),
+                    faces: Box::new(faces),
+                }
+            }
+        }
+    }
+
+    fn visibility(&self, position: Vec3, normal: Vec3, pcf_radius: u32) -> f32 {
+        let map = match self {
+            Shadow::Single(map) => map,
+            Shadow::Cube { center, faces } => {
+                // The face whose axis is the major one of the light to
+                // point vector.
+                let d = position - *center;
+                let abs = d.abs();
+                let face = if abs.x >= abs.y && abs.x >= abs.z {
+                    if d.x >= 0f32 { 0 } else { 1 }
+                } else if abs.y >= abs.z {
+                    if d.y >= 0f32 { 2 } else { 3 }
+                } else if d.z >= 0f32 {
+                    4
+                } else {
+                    5
+                };
+                &faces[face]
+            }
+        };
+        map.visibility(position, normal, pcf_radius)
+    }
+}
+
+/// Depth of the closest surface seen through each texel of a light's view.
+struct ShadowMap {
+    view_projection: Mat4,
+    size: u32,
+    depths: Vec<f32>,
+    normal_offset: f32,
+}
+
+impl ShadowMap {
+    fn render(view_projection: Mat4, size: u32, normal_offset: f32, meshes: &[Mesh]) -> Self {
+        let vs = DepthVertexShader { view_projection };
+        let viewport = Vec2::splat(size as f32);
+        let mut no_color = NoColor;
+        // Both faces cast shadows, whatever the camera culls.
+        let mut ps = PixelShaderImpl::from_point_painter(&mut no_color, size, size)
+            .with_fragment_shader(Unlit);
+        for triangle in meshes.iter().flat_map(|mesh| &mesh.triangles) {
+            ps.fill_polygon(triangle.project_to_canvas(
+                &vs,
+                viewport,
+                InterpolationMode::Perspective,
+            ));
+        }
+        Self {
+            view_projection,
+            size,
+            depths: ps.into_depth_buffer(),
+            normal_offset,
+        }
+    }
+
+    /// Fraction of the PCF kernel around `position` that is not behind the
+    /// stored depths. Points outside of the map are lit.
+    fn visibility(&self, position: Vec3, normal: Vec3, pcf_radius: u32) -> f32 {
+        let clip = self.view_projection * (position + normal * self.normal_offset).extend(1f32);
+        if clip.w <= 0f32 {
+            return 1f32;
+        }
+        let ndc = clip.truncate() / clip.w;
+        if ndc.z > 1f32 || ndc.truncate().abs().cmpgt(Vec2::ONE).any() {
+            return 1f32;
+        }
+        let texel = ((ndc.truncate() * 0.5 + 0.5) * self.size as f32)
+            .floor()
+            .as_ivec2();
+        let radius = pcf_radius as i32;
+        let max = IVec2::splat(self.size as i32 - 1);
+        let mut lit = 0u32;
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let t = (texel + IVec2::new(dx, dy)).clamp(IVec2::ZERO, max);
+                let stored = self.depths[(t.y as u32 * self.size + t.x as u32) as usize];
+                if ndc.z <= stored + DEPTH_BIAS {
+                    lit += 1;
+                }
+            }
+        }
+        lit as f32 / ((2 * radius + 1) * (2 * radius + 1)) as f32
+    }
+}
+
+/// Bounding sphere of the scene geometry.
+#[derive(Clone, Copy)]
+struct Bounds {
+    center: Vec3,
+    radius: f32,
+}
+
+impl Bounds {
+    fn of(meshes: &[Mesh]) -> Option<Self> {
+        let mut points = meshes
+            .iter()
+            .flat_map(|mesh| &mesh.triangles)
+            .flat_map(|t| [t.v0, t.v1, t.v2]);
+        let first = points.next()?;
+        let (min, max) = points.fold((first, first), |(min, max), p| (min.min(p), max.max(p)));
+        Some(Self {
+            center: (min + max) * 0.5,
+            radius: ((max - min).length() * 0.5).max(f32::EPSILON),
+        })
+    }
+}
+
+/// Any up vector not parallel to `direction`.
+fn up_for(direction: Vec3) -> Vec3 {
+    if direction.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    }
+}
+
+/// Transforms vertices into the light's clip space without shading them.
+struct DepthVertexShader {
+    view_projection: Mat4,
+}
+
+impl VertexShader for DepthVertexShader {
+    /// Nothing to interpolate but depth.
+    type Varyings = f32;
+
+    fn vertex_shader(&self, vertex: &Vertex) -> ClipVertex<f32> {
+        ClipVertex::new(self.view_projection * vertex.point.extend(1f32), 0f32)
+    }
+}
+
+/// Depth passes only keep the z-buffer.
+#[derive(Clone, Copy)]
+struct Unlit;
+
+impl FragmentShader for Unlit {
+    type Varyings = f32;
+
+    fn fragment_shader(&self, _fragment: &Fragment<f32>) -> Vec3 {
+        Vec3::ZERO
+    }
+}
+
+struct NoColor;
+
+impl PointPainter for NoColor {
+    fn draw_point(&mut self, _x: u32, _y: u32, _color: &Vec3) {}
+}
+
+#[cfg(test)]
+mod test {
+    use glam::Vec3;
+
+    use super::{ShadowMaps, ShadowSettings};
+    use crate::{
+        geometry::{mesh::Mesh, primitives::Triangle},
+        light::{Light, Lighting},
+    };
+
+    #[test]
+    fn occluder_casts_a_shadow() {
+        // A floor at y = 1 under a smaller quad at y = 0, lit from above.
+        let quad = |y: f32, half: f32| {
+            let [a, b, c, d] = [(-1f32, -1f32), (1f32, -1f32), (1f32, 1f32), (-1f32, 1f32)]
+                .map(|(x, z)| Vec3::new(x * half, y, z * half));
+            Mesh::new(vec![
+                Triangle::new(a, b, c, Vec3::ONE),
+                Triangle::new(a, c, d, Vec3::ONE),
+            ])
+        };
+        let meshes = [quad(1f32, 4f32), quad(0f32, 0.5)];
+        let up = Vec3::NEG_Y;
+        for light in [
+            Light::point(Vec3::new(0f32, -2f32, 0f32), Vec3::ONE).unwrap(),
+            Light::directional(Vec3::Y, Vec3::ONE).unwrap(),
+            Light::spot(Vec3::new(0f32, -2f32, 0f32), Vec3::Y, Vec3::ONE, 0.6, 1.2).unwrap(),
+        ] {
+            let mut lighting = Lighting::default();
+            lighting.light_mut(0).unwrap().set_enabled(false);
+            let index = lighting.add(light);
+            let shadows = ShadowMaps::render(&lighting, &meshes, ShadowSettings::default());
+            let kind = light.kind();
+            assert_eq!(
+                0f32,
+                shadows.visibility(index, Vec3::new(0f32, 1f32, 0f32), up),
+                "{kind:?} under the occluder"
+            );
+            assert_eq!(
+                1f32,
+                shadows.visibility(index, Vec3::new(2f32, 1f32, 0.3), up),
+                "{kind:?} beside the occluder"
+            );
+            assert_eq!(
+                1f32,
+                shadows.visibility(index, Vec3::new(0.1, 0f32, -0.2), up),
+                "{kind:?} on the occluder"
+            );
+        }
+    }
+}
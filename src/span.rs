@@ -0,0 +1,494 @@
+use glam::IVec2;
+
+use crate::{
+    geometry::primitives::{Pixel, Varyings},
+    operations::Interpolant,
+};
+
+/// Horizontal run of pixels whose attributes vary linearly along x.
+///
+/// Pixel `i` carries `value + value_step * i` for every attribute, the
+/// scalar and vector kernels below evaluate exactly this expression so
+/// they agree bit for bit.
+#[derive(Clone, Copy, Debug)]
+pub struct Span<V = Varyings> {
+    /// First pixel of the span.
+    pub point: IVec2,
+    pub len: usize,
+    pub depth: f32,
+    pub depth_step: f32,
+    pub z_recip: f32,
+    pub z_recip_step: f32,
+    pub varyings: V,
+    pub varyings_step: V,
+}
+
+impl<V: Interpolant> Span<V> {
+    /// The `len` pixels going from `start` to `end`, both included.
+    pub fn new(start: &Pixel<V>, end: &Pixel<V>, len: usize) -> Self {
+        let steps = len.saturating_sub(1).max(1) as f32;
+        Self {
+            point: start.point,
+            len,
+            depth: start.depth,
+            depth_step: (end.depth - start.depth) / steps,
+            z_recip: start.z_recip,
+            z_recip_step: (end.z_recip - start.z_recip) / steps,
+            varyings: start.varyings,
+            varyings_step: (end.varyings - start.varyings) / steps,
+        }
+    }
+
+    pub fn pixel(&self, i: usize) -> Pixel<V> {
+        let t = i as f32;
+        Pixel::new(
+            self.point + IVec2::new(i as i32, 0),
+            self.depth + self.depth_step * t,
+            self.z_recip + self.z_recip_step * t,
+            self.varyings + self.varyings_step * t,
+        )
+    }
+
+    pub fn pixels(&self) -> impl Iterator<Item = Pixel<V>> {
+        (0..self.len).map(|i| self.pixel(i))
+    }
+}
+
+/// Instruction set used by the span kernels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimdLevel {
+    Scalar,
+    /// 4 pixels at once, always available on x86_64.
+    Sse2,
+    /// 8 pixels at once.
+    Avx,
+}
+
+impl SimdLevel {
+    /// The widest level the running CPU supports.
+    pub fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx") {
+                return SimdLevel::Avx;
+            }
+            SimdLevel::Sse2
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        SimdLevel::Scalar
+    }
+
+    /// Every level the running CPU supports, narrowest first.
+    pub fn supported() -> Vec<Self> {
+        [SimdLevel::Scalar, SimdLevel::Sse2, SimdLevel::Avx]
+            .into_iter()
+            .filter(|level| *level as u8 <= Self::detect() as u8)
+            .collect()
+    }
+}
+
+/// Perspective corrected varyings of the pixels of a span, as written by
+/// [`shade_span`]. Each pixel holds its [`Interpolant`] channels padded to
+/// a multiple of 4, so that the vector kernels store whole registers.
+#[derive(Debug, Clone, Default)]
+pub struct SpanVaryings {
+    /// Floats per pixel, channel `c` of pixel `i` being at `i * stride + c`.
+    stride: usize,
+    values: Vec<f32>,
+    /// Channels of the span's first pixel and their steps along x, padded
+    /// with zeros to `stride`.
+    start: Vec<f32>,
+    step: Vec<f32>,
+}
+
+impl SpanVaryings {
+    fn reset<V: Interpolant>(&mut self, span: &Span<V>) {
+        self.stride = V::CHANNELS.next_multiple_of(4);
+        if self.values.len() < span.len * self.stride {
+            self.values.resize(span.len * self.stride, 0f32);
+        }
+        for (channels, v) in [
+            (&mut self.start, span.varyings),
+            (&mut self.step, span.varyings_step),
+        ] {
+            channels.clear();
+            channels.extend((0..V::CHANNELS).map(|c| v.channel(c)));
+            channels.resize(self.stride, 0f32);
+        }
+    }
+
+    /// Varyings of pixel `i`, stale unless it passed.
+    pub fn get<V: Interpolant>(&self, i: usize) -> V {
+        let pixel = &self.values[i * self.stride..][..V::CHANNELS];
+        V::from_channels(|c| pixel[c])
+    }
+}
+
+/// Depth tests every pixel of `span` against `z_buf`, the depth buffer row
+/// slice it covers. Pixels in front of the near plane or not closer than
+/// the stored depth fail, the others store their depth and get their
+/// perspective corrected varyings in `out`. `passed[i]` tells which.
+pub fn shade_span<V: Interpolant>(
+    level: SimdLevel,
+    span: &Span<V>,
+    z_buf: &mut [f32],
+    out: &mut SpanVaryings,
+    passed: &mut [bool],
+) {
+    let (z_buf, passed) = (&mut z_buf[..span.len], &mut passed[..span.len]);
+    out.reset(span);
+    let done = match level {
+        SimdLevel::Scalar => 0,
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: SSE2 is part of the x86_64 baseline.
+        SimdLevel::Sse2 => unsafe { x86::shade_span_sse2(span, z_buf, out, passed) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx => {
+            assert!(is_x86_feature_detected!("avx"), "AVX is not supported");
+            // SAFETY: AVX support was just checked.
+            unsafe { x86::shade_span_avx(span, z_buf, out, passed) }
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        _ => 0,
+    };
+    shade_span_scalar(span, done, z_buf, out, passed);
+}
+
+/// Scalar [`shade_span`] of the pixels from `first` on. Varyings are
+/// multiplied by the reciprocal of `z_recip` like the vector kernels do.
+fn shade_span_scalar<V: Interpolant>(
+    span: &Span<V>,
+    first: usize,
+    z_buf: &mut [f32],
+    out: &mut SpanVaryings,
+    passed: &mut [bool],
+) {
+    let (start, step) = (&out.start[..V::CHANNELS], &out.step[..V::CHANNELS]);
+    for i in first..span.len {
+        let t = i as f32;
+        let depth = span.depth + span.depth_step * t;
+        passed[i] = depth >= 0f32 && depth < z_buf[i];
+        if !passed[i] {
+            continue;
+        }
+        z_buf[i] = depth;
+        let w = 1f32 / (span.z_recip + span.z_recip_step * t);
+        let pixel = &mut out.values[i * out.stride..][..V::CHANNELS];
+        for c in 0..V::CHANNELS {
+            pixel[c] = (start[c] + step[c] * t) * w;
+        }
+    }
+}
+
+/// Quantizes `src` channels to 8 bits like [`crate::pixels::PixelFormat::encode`].
+pub fn pack_unorm8(level: SimdLevel, src: &[f32], dst: &mut [u8]) {
+    let dst = &mut dst[..src.len()];
+    let done = match level {
+        SimdLevel::Scalar => 0,
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: SSE2 is part of the x86_64 baseline.
+        SimdLevel::Sse2 => unsafe { x86::pack_unorm8_sse2(src, dst) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx => {
+            assert!(is_x86_feature_detected!("avx"), "AVX is not supported");
+            // SAFETY: AVX support was just checked.
+            unsafe { x86::pack_unorm8_avx(src, dst) }
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        _ => 0,
+    };
+    for (d, s) in dst[done..].iter_mut().zip(&src[done..]) {
+        *d = (s * 255f32).clamp(0f32, 255f32) as u8;
+    }
+}
+
+/// Vector kernels, each returning how many leading elements it handled
+/// and leaving the remainder to the scalar code.
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::{Span, SpanVaryings};
+
+    #[target_feature(enable = "sse2")]
+    pub(super) fn shade_span_sse2<V>(
+        span: &Span<V>,
+        z_buf: &mut [f32],
+        out: &mut SpanVaryings,
+        passed: &mut [bool],
+    ) -> usize {
+        const LANES: usize = 4;
+        let chunks = span.len / LANES;
+        let lane_index = _mm_setr_ps(0f32, 1f32, 2f32, 3f32);
+        let linear = |value: f32, step: f32, t: __m128| {
+            _mm_add_ps(_mm_set1_ps(value), _mm_mul_ps(_mm_set1_ps(step), t))
+        };
+        for chunk in 0..chunks {
+            let i = chunk * LANES;
+            let t = _mm_add_ps(_mm_set1_ps(i as f32), lane_index);
+            let depth = linear(span.depth, span.depth_step, t);
+            // SAFETY: `i + LANES <= span.len`, the length of `z_buf`.
+            let stored = unsafe { _mm_loadu_ps(z_buf.as_ptr().add(i)) };
+            let pass = _mm_and_ps(
+                _mm_cmpge_ps(depth, _mm_setzero_ps()),
+                _mm_cmplt_ps(depth, stored),
+            );
+            let mask = _mm_movemask_ps(pass);
+            for lane in 0..LANES {
+                passed[i + lane] = mask & (1 << lane) != 0;
+            }
+            if mask == 0 {
+                continue;
+            }
+            let depth = _mm_or_ps(_mm_and_ps(pass, depth), _mm_andnot_ps(pass, stored));
+            // SAFETY: as for the load above.
+            unsafe { _mm_storeu_ps(z_buf.as_mut_ptr().add(i), depth) };
+
+            let w = _mm_div_ps(
+                _mm_set1_ps(1f32),
+                linear(span.z_recip, span.z_recip_step, t),
+            );
+            let stride = out.stride;
+            let values = &mut out.values[i * stride..(i + LANES) * stride];
+            // Channels are computed 4 at a time, one register each, then
+            // transposed to be stored pixel by pixel.
+            let groups = out.start.chunks_exact(4).zip(out.step.chunks_exact(4));
+            for (group, (start, step)) in groups.enumerate() {
+                let channel = |c: usize| _mm_mul_ps(linear(start[c], step[c], t), w);
+                let pixels = transpose([channel(0), channel(1), channel(2), channel(3)]);
+                for (lane, pixel) in pixels.into_iter().enumerate() {
+                    // SAFETY: `lane < LANES` and `4 * group + 4 <= stride`,
+                    // within the `LANES * stride` floats of `values`.
+                    unsafe {
+                        let dst = values.as_mut_ptr().add(lane * stride + 4 * group);
+                        _mm_storeu_ps(dst, pixel);
+                    }
+                }
+            }
+        }
+        chunks * LANES
+    }
+
+    #[target_feature(enable = "avx")]
+    pub(super) fn shade_span_avx<V>(
+        span: &Span<V>,
+        z_buf: &mut [f32],
+        out: &mut SpanVaryings,
+        passed: &mut [bool],
+    ) -> usize {
+        const LANES: usize = 8;
+        let chunks = span.len / LANES;
+        let lane_index = _mm256_setr_ps(0f32, 1f32, 2f32, 3f32, 4f32, 5f32, 6f32, 7f32);
+        let linear = |value: f32, step: f32, t: __m256| {
+            _mm256_add_ps(
+                _mm256_set1_ps(value),
+                _mm256_mul_ps(_mm256_set1_ps(step), t),
+            )
+        };
+        for chunk in 0..chunks {
+            let i = chunk * LANES;
+            let t = _mm256_add_ps(_mm256_set1_ps(i as f32), lane_index);
+            let depth = linear(span.depth, span.depth_step, t);
+            // SAFETY: `i + LANES <= span.len`, the length of `z_buf`.
+            let stored = unsafe { _mm256_loadu_ps(z_buf.as_ptr().add(i)) };
+            let pass = _mm256_and_ps(
+                _mm256_cmp_ps::<_CMP_GE_OQ>(depth, _mm256_setzero_ps()),
+                _mm256_cmp_ps::<_CMP_LT_OQ>(depth, stored),
+            );
+            let mask = _mm256_movemask_ps(pass);
+            for lane in 0..LANES {
+                passed[i + lane] = mask & (1 << lane) != 0;
+            }
+            if mask == 0 {
+                continue;
+            }
+            let depth = _mm256_blendv_ps(stored, depth, pass);
+            // SAFETY: as for the load above.
+            unsafe { _mm256_storeu_ps(z_buf.as_mut_ptr().add(i), depth) };
+
+            let w = _mm256_div_ps(
+                _mm256_set1_ps(1f32),
+                linear(span.z_recip, span.z_recip_step, t),
+            );
+            let stride = out.stride;
+            let values = &mut out.values[i * stride..(i + LANES) * stride];
+            // As with SSE2, the low and high halves of the channel
+            // registers holding lanes 0 to 3 and 4 to 7.
+            let groups = out.start.chunks_exact(4).zip(out.step.chunks_exact(4));
+            for (group, (start, step)) in groups.enumerate() {
+                let channel = |c: usize| _mm256_mul_ps(linear(start[c], step[c], t), w);
+                let rows = [channel(0), channel(1), channel(2), channel(3)];
+                let low = transpose(rows.map(|row| _mm256_castps256_ps128(row)));
+                let high = transpose(rows.map(|row| _mm256_extractf128_ps::<1>(row)));
+                for lane in 0..4 {
+                    // SAFETY: `lane + 4 < LANES` and `4 * group + 4 <= stride`,
+                    // within the `LANES * stride` floats of `values`.
+                    unsafe {
+                        let dst = values.as_mut_ptr().add(lane * stride + 4 * group);
+                        _mm_storeu_ps(dst, low[lane]);
+                        _mm_storeu_ps(dst.add(4 * stride), high[lane]);
+                    }
+                }
+            }
+        }
+        chunks * LANES
+    }
+
+    /// Turns 4 channels of 4 pixels into the 4 channels of each pixel.
+    #[target_feature(enable = "sse2")]
+    fn transpose([c0, c1, c2, c3]: [__m128; 4]) -> [__m128; 4] {
+        let (low01, low23) = (_mm_unpacklo_ps(c0, c1), _mm_unpacklo_ps(c2, c3));
+        let (high01, high23) = (_mm_unpackhi_ps(c0, c1), _mm_unpackhi_ps(c2, c3));
+        [
+            _mm_movelh_ps(low01, low23),
+            _mm_movehl_ps(low23, low01),
+            _mm_movelh_ps(high01, high23),
+            _mm_movehl_ps(high23, high01),
+        ]
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) fn pack_unorm8_sse2(src: &[f32], dst: &mut [u8]) -> usize {
+        const LANES: usize = 4;
+        let chunks = src.len() / LANES;
+        for chunk in 0..chunks {
+            let i = chunk * LANES;
+            // SAFETY: `i + LANES <= src.len()`.
+            let v = unsafe { _mm_loadu_ps(src.as_ptr().add(i)) };
+            // `max` first so that NaN maps to 0 like the scalar code.
+            let v = _mm_min_ps(
+                _mm_max_ps(_mm_mul_ps(v, _mm_set1_ps(255f32)), _mm_setzero_ps()),
+                _mm_set1_ps(255f32),
+            );
+            let words = _mm_packs_epi32(_mm_cvttps_epi32(v), _mm_setzero_si128());
+            let bytes = _mm_packus_epi16(words, _mm_setzero_si128());
+            dst[i..i + LANES].copy_from_slice(&_mm_cvtsi128_si32(bytes).to_le_bytes());
+        }
+        chunks * LANES
+    }
+
+    #[target_feature(enable = "avx")]
+    pub(super) fn pack_unorm8_avx(src: &[f32], dst: &mut [u8]) -> usize {
+        const LANES: usize = 8;
+        let chunks = src.len() / LANES;
+        for chunk in 0..chunks {
+            let i = chunk * LANES;
+            // SAFETY: `i + LANES <= src.len()`.
+            let v = unsafe { _mm256_loadu_ps(src.as_ptr().add(i)) };
+            // `max` first so that NaN maps to 0 like the scalar code.
+            let v = _mm256_min_ps(
+                _mm256_max_ps(
+                    _mm256_mul_ps(v, _mm256_set1_ps(255f32)),
+                    _mm256_setzero_ps(),
+                ),
+                _mm256_set1_ps(255f32),
+            );
+            let ints = _mm256_cvttps_epi32(v);
+            let words = _mm_packs_epi32(
+                _mm256_castsi256_si128(ints),
+                _mm256_extractf128_si256::<1>(ints),
+            );
+            let bytes = _mm_packus_epi16(words, _mm_setzero_si128());
+            dst[i..i + LANES].copy_from_slice(&_mm_cvtsi128_si64(bytes).to_le_bytes());
+        }
+        chunks * LANES
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fmt::Debug;
+
+    use glam::{IVec2, Vec2, Vec3};
+
+    use super::{SimdLevel, Span, SpanVaryings, pack_unorm8, shade_span};
+    use crate::{geometry::primitives::Varyings, operations::Interpolant};
+
+    /// Shades `span` at every supported level, checking that they agree
+    /// bit for bit and with [`super::Pixel::corrected_varyings`].
+    fn check_kernels<V: Interpolant + PartialEq + Debug>(span: &Span<V>) {
+        let z_init: Vec<f32> = (0..span.len).map(|i| [1f32, 0.3, 0.5][i % 3]).collect();
+        let run = |level| {
+            let mut z_buf = z_init.clone();
+            let mut out = SpanVaryings::default();
+            let mut passed = vec![false; span.len];
+            shade_span(level, span, &mut z_buf, &mut out, &mut passed);
+            // Failing pixels hold unspecified varyings.
+            let varyings: Vec<Option<V>> = (0..span.len)
+                .map(|i| passed[i].then(|| out.get(i)))
+                .collect();
+            (z_buf, varyings, passed)
+        };
+        let scalar = run(SimdLevel::Scalar);
+        assert!(!scalar.2[0], "pixels in front of the near plane must fail");
+        assert!(scalar.2.contains(&true) && scalar.2.contains(&false));
+        for (i, varyings) in scalar.1.iter().enumerate() {
+            let Some(varyings) = varyings else {
+                continue;
+            };
+            let expected = span.pixel(i).corrected_varyings();
+            for c in 0..V::CHANNELS {
+                let (got, expected) = (varyings.channel(c), expected.channel(c));
+                assert!((got - expected).abs() <= 1e-6 * expected.abs().max(1f32));
+            }
+        }
+        for level in SimdLevel::supported() {
+            assert_eq!(scalar, run(level), "{level:?}");
+        }
+    }
+
+    #[test]
+    fn kernels_agree_with_scalar() {
+        // 19 pixels leave a remainder for every vector width.
+        let span = Span {
+            point: IVec2::new(3, 5),
+            len: 19,
+            depth: -0.05,
+            depth_step: 0.06,
+            z_recip: 0.5,
+            z_recip_step: 0.01,
+            varyings: Varyings::new(
+                Vec3::new(0.1, 0.2, 0.3),
+                Vec3::new(-1f32, 0.5, 0.25),
+                Vec3::Y,
+                Vec2::new(0.75, -2f32),
+            ),
+            varyings_step: Varyings::new(
+                Vec3::new(0.01, -0.02, 0.005),
+                Vec3::splat(0.03),
+                Vec3::new(0.1, 0f32, -0.1),
+                Vec2::new(-0.04, 0.125),
+            ),
+        };
+        check_kernels(&span);
+        // A single channel, padded to a whole register.
+        check_kernels(&Span {
+            point: span.point,
+            len: span.len,
+            depth: span.depth,
+            depth_step: span.depth_step,
+            z_recip: span.z_recip,
+            z_recip_step: span.z_recip_step,
+            varyings: 2f32,
+            varyings_step: -0.25,
+        });
+    }
+
+    #[test]
+    fn pack_matches_scalar() {
+        let src: Vec<f32> = (0..21)
+            .map(|i| i as f32 * 0.07 - 0.2)
+            .chain([f32::NAN, f32::INFINITY, -f32::INFINITY])
+            .collect();
+        let run = |level| {
+            let mut dst = vec![0u8; src.len()];
+            pack_unorm8(level, &src, &mut dst);
+            dst
+        };
+        let scalar = run(SimdLevel::Scalar);
+        assert_eq!(&scalar[..4], &[0, 0, 0, 2]);
+        for level in SimdLevel::supported() {
+            assert_eq!(scalar, run(level), "{level:?}");
+        }
+    }
+}
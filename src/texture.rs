@@ -0,0 +1,443 @@
+use std::{fs, path::Path};
+
+use glam::{Vec2, Vec3};
+
+use crate::error::{Error, Result};
+
+mod formats;
+
+/// How texture coordinates outside of `[0, 1]` are brought back in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressMode {
+    /// Tiles the texture.
+    Repeat,
+    /// Stretches the border texels.
+    ClampToEdge,
+    /// Tiles the texture, flipping every other copy.
+    MirroredRepeat,
+}
+
+impl TryFrom<u32> for AddressMode {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self> {
+        Ok(match value {
+            0 => AddressMode::Repeat,
+            1 => AddressMode::ClampToEdge,
+            2 => AddressMode::MirroredRepeat,
+            _ => {
+                return Err(Error::invalid_argument(format!(
+                    "unknown texture address mode {value}"
+                )));
+            }
+        })
+    }
+}
+
+impl AddressMode {
+    /// Maps the texel coordinate `i` into `0..size`.
+    fn wrap(self, i: i32, size: u32) -> u32 {
+        let size = size as i32;
+        let i = match self {
+            AddressMode::Repeat => i.rem_euclid(size),
+            AddressMode::ClampToEdge => i.clamp(0, size - 1),
+            AddressMode::MirroredRepeat => {
+                let i = i.rem_euclid(2 * size);
+                if i >= size { 2 * size - 1 - i } else { i }
+            }
+        };
+        i as u32
+    }
+}
+
+/// How texels are combined into a sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFilter {
+    /// Closest texel of the full resolution image.
+    Nearest,
+    /// Blends the four closest texels of the closest mipmap level.
+    Bilinear,
+    /// Blends bilinear samples of the two closest mipmap levels.
+    Trilinear,
+    /// Averages up to [`MAX_ANISOTROPY`] trilinear samples along the
+    /// direction the texture is stretched in, keeping surfaces seen at
+    /// grazing angles sharp.
+    Anisotropic,
+}
+
+impl TryFrom<u32> for TextureFilter {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self> {
+        Ok(match value {
+            0 => TextureFilter::Nearest,
+            1 => TextureFilter::Bilinear,
+            2 => TextureFilter::Trilinear,
+            3 => TextureFilter::Anisotropic,
+            _ => {
+                return Err(Error::invalid_argument(format!(
+                    "unknown texture filter {value}"
+                )));
+            }
+        })
+    }
+}
+
+/// Largest supported texture side, in texels.
+pub const MAX_TEXTURE_SIZE: u32 = 16384;
+
+/// Most trilinear samples taken by [`TextureFilter::Anisotropic`].
+pub const MAX_ANISOTROPY: u32 = 8;
+
+/// Image mapped on surfaces through their UV coordinates, `(0, 0)` being
+/// the top-left corner and `(1, 1)` the bottom-right one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Texture {
+    /// Mipmap chain, each level halving the previous one down to a single
+    /// texel.
+    levels: Vec<MipLevel>,
+    address_mode: AddressMode,
+    filter: TextureFilter,
+}
+
+impl Texture {
+    pub fn new(width: u32, height: u32, texels: Vec<Vec3>) -> Result<Self> {
+        if !(1..=MAX_TEXTURE_SIZE).contains(&width) || !(1..=MAX_TEXTURE_SIZE).contains(&height) {
+            return Err(Error::invalid_argument(format!(
+                "texture sides must be within 1..={MAX_TEXTURE_SIZE}, got {width}x{height}"
+            )));
+        }
+        if texels.len() != width as usize * height as usize {
+            return Err(Error::invalid_argument(format!(
+                "{width}x{height} texture needs {} texels, got {}",
+                width as usize * height as usize,
+                texels.len()
+            )));
+        }
+        let mut levels = vec![MipLevel {
+            width,
+            height,
+            texels,
+        }];
+        while let Some(level) = levels.last().and_then(MipLevel::downsample) {
+            levels.push(level);
+        }
+        Ok(Self {
+            levels,
+            address_mode: AddressMode::Repeat,
+            filter: TextureFilter::Trilinear,
+        })
+    }
+
+    /// Texture from 8 bits per channel RGBA rows `pitch` bytes apart, the
+    /// alpha channel being ignored.
+    pub fn from_rgba8(width: u32, height: u32, pitch: usize, data: &[u8]) -> Result<Self> {
+        let row_len = width as usize * 4;
+        if pitch < row_len {
+            return Err(Error::invalid_argument(format!(
+                "row pitch {pitch} is smaller than a {width} texels row ({row_len} bytes)"
+            )));
+        }
+        let required = Self::rgba8_len(width, height, pitch)?;
+        if data.len() < required {
+            return Err(Error::invalid_argument(format!(
+                "texture data holds {} bytes, {required} required",
+                data.len()
+            )));
+        }
+        let texels = (0..height as usize)
+            .flat_map(|y| data[y * pitch..y * pitch + row_len].chunks_exact(4))
+            .map(|rgba| Vec3::new(rgba[0] as f32, rgba[1] as f32, rgba[2] as f32) / 255f32)
+            .collect();
+        Self::new(width, height, texels)
+    }
+
+    /// Bytes spanned by `height` rows of RGBA texels, the last one without
+    /// its padding.
+    pub fn rgba8_len(width: u32, height: u32, pitch: usize) -> Result<usize> {
+        match height {
+            0 => Some(0),
+            _ => pitch
+                .checked_mul(height as usize - 1)
+                .and_then(|len| len.checked_add(width as usize * 4)),
+        }
+        // Slices cannot be any larger.
+        .filter(|&len| len <= isize::MAX as usize)
+        .ok_or_else(|| {
+            Error::invalid_argument(format!(
+                "{height} rows of pitch {pitch} overflow the address space"
+            ))
+        })
+    }
+
+    /// Loads a binary or ASCII PPM, or an uncompressed or RLE TGA image.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|err| Error::io(path, err))?;
+        Self::decode(&bytes)
+            .map_err(|err| Error::new(err.status, format!("{}: {}", path.display(), err.message)))
+    }
+
+    /// Decodes an in-memory image, see [`Texture::load`].
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let (width, height, texels) = match bytes {
+            [b'P', b'3' | b'6', ..] => formats::decode_ppm(bytes)?,
+            _ => formats::decode_tga(bytes)?,
+        };
+        Self::new(width, height, texels)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.levels[0].height
+    }
+
+    /// Number of mipmap levels, the full resolution one included.
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn address_mode(&self) -> AddressMode {
+        self.address_mode
+    }
+
+    pub fn set_address_mode(&mut self, address_mode: AddressMode) {
+        self.address_mode = address_mode;
+    }
+
+    pub fn filter(&self) -> TextureFilter {
+        self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: TextureFilter) {
+        self.filter = filter;
+    }
+
+    pub fn texel(&self, x: u32, y: u32) -> Vec3 {
+        self.levels[0].texel(x, y)
+    }
+
+    /// Filtered color around `uv`, `duv_dx` and `duv_dy` being how much
+    /// `uv` changes from one pixel to the next along x and y.
+    pub fn sample(&self, uv: Vec2, duv_dx: Vec2, duv_dy: Vec2) -> Vec3 {
+        let mode = self.address_mode;
+        // Footprint of the pixel in full resolution texels.
+        let size = Vec2::new(self.width() as f32, self.height() as f32);
+        let (dx, dy) = (duv_dx * size, duv_dy * size);
+        match self.filter {
+            TextureFilter::Nearest => self.levels[0].nearest(uv, mode),
+            TextureFilter::Bilinear => {
+                let level = self.clamp_lod(lod(dx.length().max(dy.length()))).round();
+                self.levels[level as usize].bilinear(uv, mode)
+            }
+            TextureFilter::Trilinear => self.trilinear(uv, lod(dx.length().max(dy.length()))),
+            TextureFilter::Anisotropic => {
+                let (major, minor) = if dx.length() >= dy.length() {
+                    (duv_dx, dy.length())
+                } else {
+                    (duv_dy, dx.length())
+                };
+                let major_len = (major * size).length();
+                let probes = (major_len / minor.max(f32::MIN_POSITIVE))
+                    .ceil()
+                    .clamp(1f32, MAX_ANISOTROPY as f32);
+                // Each probe covers its share of the major axis.
+                let lod = lod((major_len / probes).max(minor));
+                let n = probes as u32;
+                (0..n)
+                    .map(|i| {
+                        let t = (i as f32 + 0.5) / probes - 0.5;
+                        self.trilinear(uv + major * t, lod)
+                    })
+                    .sum::<Vec3>()
+                    / probes
+            }
+        }
+    }
+
+    fn clamp_lod(&self, lod: f32) -> f32 {
+        // `max` first, turning NaN into 0.
+        lod.max(0f32).min((self.levels.len() - 1) as f32)
+    }
+
+    fn trilinear(&self, uv: Vec2, lod: f32) -> Vec3 {
+        let lod = self.clamp_lod(lod);
+        let level = lod as usize;
+        let fine = self.levels[level].bilinear(uv, self.address_mode);
+        match self.levels.get(level + 1) {
+            Some(coarse) if lod > level as f32 => {
+                fine.lerp(coarse.bilinear(uv, self.address_mode), lod - level as f32)
+            }
+            _ => fine,
+        }
+    }
+}
+
+/// Mipmap level selected by a footprint `texels` wide.
+fn lod(texels: f32) -> f32 {
+    texels.log2()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct MipLevel {
+    width: u32,
+    height: u32,
+    /// Linear colors, row by row from the top.
+    texels: Vec<Vec3>,
+}
+
+impl MipLevel {
+    fn texel(&self, x: u32, y: u32) -> Vec3 {
+        self.texels[(y * self.width + x) as usize]
+    }
+
+    /// Half sized level averaging blocks of 2x2 texels, the last row or
+    /// column of odd sizes being dropped. `None` once down to one texel.
+    fn downsample(&self) -> Option<Self> {
+        if self.width == 1 && self.height == 1 {
+            return None;
+        }
+        let (width, height) = ((self.width / 2).max(1), (self.height / 2).max(1));
+        let texels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (x0, y0) = (2 * x, 2 * y);
+                let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+                (self.texel(x0, y0) + self.texel(x1, y0) + self.texel(x0, y1) + self.texel(x1, y1))
+                    * 0.25
+            })
+            .collect();
+        Some(Self {
+            width,
+            height,
+            texels,
+        })
+    }
+
+    fn nearest(&self, uv: Vec2, mode: AddressMode) -> Vec3 {
+        let at = (uv * Vec2::new(self.width as f32, self.height as f32))
+            .floor()
+            .as_ivec2();
+        self.texel(mode.wrap(at.x, self.width), mode.wrap(at.y, self.height))
+    }
+
+    fn bilinear(&self, uv: Vec2, mode: AddressMode) -> Vec3 {
+        // Texel centers sit at half integer coordinates.
+        let at = uv * Vec2::new(self.width as f32, self.height as f32) - 0.5;
+        let base = at.floor();
+        // Infinite coordinates leave NaN weights, which `max` turns into 0.
+        let t = (at - base).max(Vec2::ZERO);
+        // Huge UVs saturate, texels that far are not distinguishable anyway.
+        let base = base.as_ivec2();
+        let [x0, x1] = [base.x, base.x.saturating_add(1)].map(|x| mode.wrap(x, self.width));
+        let [y0, y1] = [base.y, base.y.saturating_add(1)].map(|y| mode.wrap(y, self.height));
+        let top = self.texel(x0, y0).lerp(self.texel(x1, y0), t.x);
+        let bottom = self.texel(x0, y1).lerp(self.texel(x1, y1), t.x);
+        top.lerp(bottom, t.y)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glam::{Vec2, Vec3};
+
+    use super::{AddressMode, Texture, TextureFilter};
+
+    #[test]
+    fn sample_rgba8_rows() {
+        // Two rows of two texels with two bytes of padding each.
+        #[rustfmt::skip]
+        let data = [
+            255, 0, 0, 9,   0, 255, 0, 9,   7, 7,
+            0, 0, 255, 9,   255, 255, 255, 9,
+        ];
+        let mut texture = Texture::from_rgba8(2, 2, 10, &data).unwrap();
+        texture.set_filter(TextureFilter::Nearest);
+        let nearest = |texture: &Texture, u: f32, v: f32| {
+            texture.sample(Vec2::new(u, v), Vec2::ZERO, Vec2::ZERO)
+        };
+        assert_eq!(Vec3::X, nearest(&texture, 0.25, 0.25));
+        assert_eq!(Vec3::Z, nearest(&texture, 0.25, 0.75));
+        assert_eq!(Vec3::Y, nearest(&texture, -0.25, 1.25));
+        texture.set_address_mode(AddressMode::ClampToEdge);
+        assert_eq!(Vec3::Z, nearest(&texture, -0.25, 1.25));
+        texture.set_address_mode(AddressMode::MirroredRepeat);
+        assert_eq!(Vec3::X, nearest(&texture, -0.25, 1.75));
+        assert!(Texture::from_rgba8(2, 2, 10, &data[..17]).is_err());
+    }
+
+    #[test]
+    fn minified_checkerboard_fades_to_gray() {
+        let texels = (0..64)
+            .map(|i| Vec3::splat(((i % 8 + i / 8) % 2) as f32))
+            .collect();
+        let mut texture = Texture::new(8, 8, texels).unwrap();
+        assert_eq!(4, texture.level_count());
+        // Texel centers are exact when magnified, whatever the filter.
+        let center = Vec2::splat(1.5 / 8f32);
+        let magnified = Vec2::new(0.01, 0f32);
+        for filter in [
+            TextureFilter::Nearest,
+            TextureFilter::Bilinear,
+            TextureFilter::Trilinear,
+            TextureFilter::Anisotropic,
+        ] {
+            texture.set_filter(filter);
+            assert_eq!(
+                Vec3::ZERO,
+                texture.sample(center, magnified, magnified.perp())
+            );
+        }
+        // A pixel spanning several checks averages them.
+        let minified = Vec2::new(0.5, 0f32);
+        texture.set_filter(TextureFilter::Trilinear);
+        let gray = texture.sample(center, minified, minified.perp());
+        assert!(
+            (gray - Vec3::splat(0.5)).abs().max_element() < 1e-6,
+            "{gray}"
+        );
+        // Horizontal stripes squashed only along x stay sharp with
+        // anisotropic filtering, the single trilinear sample blurs them.
+        let texels = (0..64).map(|i| Vec3::splat((i / 8 % 2) as f32)).collect();
+        let mut stripes = Texture::new(8, 8, texels).unwrap();
+        let squashed = (Vec2::new(0.5, 0f32), Vec2::new(0f32, 1f32 / 64f32));
+        let trilinear = stripes.sample(center, squashed.0, squashed.1);
+        stripes.set_filter(TextureFilter::Anisotropic);
+        let anisotropic = stripes.sample(center, squashed.0, squashed.1);
+        assert_eq!(Vec3::ONE, anisotropic);
+        assert!(trilinear.x < 0.75, "{trilinear}");
+    }
+
+    #[test]
+    fn huge_uvs_stay_in_range() {
+        let mut texture = Texture::new(2, 2, vec![Vec3::ONE; 4]).unwrap();
+        let uvs = [
+            Vec2::new(1e12, 0f32),
+            Vec2::new(-1e12, -3e9),
+            Vec2::splat(f32::MAX),
+            Vec2::splat(f32::MIN),
+        ];
+        for filter in [
+            TextureFilter::Nearest,
+            TextureFilter::Bilinear,
+            TextureFilter::Trilinear,
+            TextureFilter::Anisotropic,
+        ] {
+            texture.set_filter(filter);
+            for mode in [
+                AddressMode::Repeat,
+                AddressMode::ClampToEdge,
+                AddressMode::MirroredRepeat,
+            ] {
+                texture.set_address_mode(mode);
+                for uv in uvs {
+                    let duv = Vec2::new(0.25, 0f32);
+                    assert_eq!(Vec3::ONE, texture.sample(uv, duv, duv.perp()));
+                }
+            }
+        }
+    }
+}
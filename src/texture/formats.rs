@@ -0,0 +1,211 @@
+//! Minimal decoders for the image formats textures can be loaded from.
+
+use glam::Vec3;
+
+use crate::{
+    error::{Error, Result},
+    texture::MAX_TEXTURE_SIZE,
+};
+
+type Image = (u32, u32, Vec<Vec3>);
+
+fn invalid(format: &str, message: impl std::fmt::Display) -> Error {
+    Error::invalid_argument(format!("invalid {format} image: {message}"))
+}
+
+/// Binary (`P6`) or ASCII (`P3`) portable pixmap.
+pub fn decode_ppm(bytes: &[u8]) -> Result<Image> {
+    let mut pos = 2;
+    let mut header = [0u32; 3];
+    for value in &mut header {
+        *value = ppm_number(bytes, &mut pos)?;
+    }
+    let [width, height, max] = header;
+    if !(1..=65535).contains(&max) {
+        return Err(invalid("PPM", format!("maximum value {max} out of range")));
+    }
+    check_size("PPM", width, height)?;
+    let count = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|texels| texels.checked_mul(3))
+        .ok_or_else(|| invalid("PPM", format!("{width}x{height} texels overflow")))?;
+    let samples: Vec<u32> = if bytes[1] == b'6' {
+        // A single whitespace separates the header from the samples.
+        pos += 1;
+        let size = if max < 256 { 1 } else { 2 };
+        let data = bytes
+            .get(pos..)
+            .filter(|data| count.checked_mul(size).is_some_and(|len| data.len() >= len))
+            .ok_or_else(|| invalid("PPM", "truncated samples"))?;
+        data.chunks_exact(size)
+            .take(count)
+            .map(|s| s.iter().fold(0, |acc, &b| acc << 8 | b as u32))
+            .collect()
+    } else {
+        (0..count)
+            .map(|_| ppm_number(bytes, &mut pos))
+            .collect::<Result<_>>()?
+    };
+    let scale = (max as f32).recip();
+    let texels = samples
+        .chunks_exact(3)
+        .map(|rgb| Vec3::new(rgb[0] as f32, rgb[1] as f32, rgb[2] as f32) * scale)
+        .collect();
+    Ok((width, height, texels))
+}
+
+/// Checked before allocating anything for a crafted header.
+fn check_size(format: &str, width: u32, height: u32) -> Result<()> {
+    if !(1..=MAX_TEXTURE_SIZE).contains(&width) || !(1..=MAX_TEXTURE_SIZE).contains(&height) {
+        return Err(invalid(
+            format,
+            format!("sides must be within 1..={MAX_TEXTURE_SIZE}, got {width}x{height}"),
+        ));
+    }
+    Ok(())
+}
+
+/// Next decimal number, skipping whitespace and `#` comments.
+fn ppm_number(bytes: &[u8], pos: &mut usize) -> Result<u32> {
+    loop {
+        match bytes.get(*pos) {
+            Some(b) if b.is_ascii_whitespace() => *pos += 1,
+            Some(b'#') => {
+                while bytes.get(*pos).is_some_and(|&b| b != b'\n') {
+                    *pos += 1;
+                }
+            }
+            _ => break,
+        }
+    }
+    let start = *pos;
+    while bytes.get(*pos).is_some_and(u8::is_ascii_digit) {
+        *pos += 1;
+    }
+    std::str::from_utf8(&bytes[start..*pos])
+        .ok()
+        .and_then(|digits| digits.parse().ok())
+        .ok_or_else(|| invalid("PPM", format!("expected a number at byte {start}")))
+}
+
+/// Truecolor or grayscale TGA, either uncompressed or run-length encoded.
+pub fn decode_tga(bytes: &[u8]) -> Result<Image> {
+    let header = bytes
+        .get(..18)
+        .ok_or_else(|| invalid("TGA", "truncated header"))?;
+    let id_len = header[0] as usize;
+    let color_map_type = header[1];
+    let image_type = header[2];
+    let width = u16::from_le_bytes([header[12], header[13]]) as u32;
+    let height = u16::from_le_bytes([header[14], header[15]]) as u32;
+    let depth = header[16];
+    let descriptor = header[17];
+    check_size("TGA", width, height)?;
+    if color_map_type != 0 {
+        return Err(invalid("TGA", "color mapped images are not supported"));
+    }
+    let (rle, gray) = match image_type {
+        2 => (false, false),
+        3 => (false, true),
+        10 => (true, false),
+        11 => (true, true),
+        _ => {
+            return Err(invalid(
+                "TGA",
+                format!("unsupported image type {image_type}"),
+            ));
+        }
+    };
+    let size = match (gray, depth) {
+        (true, 8) => 1,
+        (false, 24) => 3,
+        (false, 32) => 4,
+        _ => return Err(invalid("TGA", format!("unsupported pixel depth {depth}"))),
+    };
+    let mut data = bytes
+        .get(18 + id_len..)
+        .ok_or_else(|| invalid("TGA", "truncated image ID"))?;
+    let count = width as usize * height as usize;
+    // Grown as data is read, its size coming from an untrusted header.
+    let mut pixels = Vec::new();
+    let truncated = || invalid("TGA", "truncated pixel data");
+    while pixels.len() < count * size {
+        if !rle {
+            let raw = data.get(..count * size).ok_or_else(truncated)?;
+            pixels.extend_from_slice(raw);
+            break;
+        }
+        let (&packet, rest) = data.split_first().ok_or_else(truncated)?;
+        let n = (packet & 0x7f) as usize + 1;
+        if packet & 0x80 != 0 {
+            let pixel = rest.get(..size).ok_or_else(truncated)?;
+            (0..n).for_each(|_| pixels.extend_from_slice(pixel));
+            data = &rest[size..];
+        } else {
+            pixels.extend_from_slice(rest.get(..n * size).ok_or_else(truncated)?);
+            data = &rest[n * size..];
+        }
+    }
+    pixels.truncate(count * size);
+    let mut texels: Vec<Vec3> = pixels
+        .chunks_exact(size)
+        .map(|p| match p {
+            [l] => Vec3::splat(*l as f32),
+            [b, g, r, ..] => Vec3::new(*r as f32, *g as f32, *b as f32),
+            _ => unreachable!(),
+        } / 255f32)
+        .collect();
+    // Rows are stored bottom up unless bit 5 of the descriptor is set.
+    if descriptor & 0x20 == 0 {
+        texels = texels
+            .chunks_exact(width as usize)
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+    }
+    Ok((width, height, texels))
+}
+
+#[cfg(test)]
+mod test {
+    use glam::Vec3;
+
+    use super::{decode_ppm, decode_tga};
+
+    #[test]
+    fn ppm_ascii_and_binary_agree() {
+        let ascii = b"P3\n# comment\n2 1\n255\n255 0 0  0 0 255\n";
+        let mut binary = b"P6 2 1 255\n".to_vec();
+        binary.extend_from_slice(&[255, 0, 0, 0, 0, 255]);
+        let expected = (2, 1, vec![Vec3::X, Vec3::Z]);
+        assert_eq!(expected, decode_ppm(ascii).unwrap());
+        assert_eq!(expected, decode_ppm(&binary).unwrap());
+        assert!(decode_ppm(&binary[..binary.len() - 1]).is_err());
+        // Would overflow the sample count on 32-bit targets.
+        assert!(decode_ppm(b"P6 4294967295 4294967295 255\n").is_err());
+    }
+
+    #[test]
+    fn tga_rle_bottom_up() {
+        // 1x2 BGR image: a run of one red pixel, then a raw blue one. The
+        // first stored row is the bottom one.
+        let mut tga = vec![0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 2, 0, 24, 0];
+        tga.extend_from_slice(&[0x80, 0, 0, 255, 0x00, 255, 0, 0]);
+        assert_eq!((1, 2, vec![Vec3::Z, Vec3::X]), decode_tga(&tga).unwrap());
+    }
+
+    #[test]
+    fn tga_oversized_header() {
+        // 65535x65535 at 32 bits per pixel, without any pixel data.
+        let tga = [
+            0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 32, 0, 0, 0, 0, 0,
+        ];
+        let err = decode_tga(&tga).unwrap_err();
+        assert!(
+            err.message.contains("sides must be within"),
+            "{}",
+            err.message
+        );
+    }
+}
@@ -1,44 +1,463 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use glam::IVec2;
+
 use crate::{
+    callback::FragmentCallback,
     camera::Camera,
-    geometry::primitives::Triangle,
-    painter::PolygonFiller,
-    pixels::PixelBuffer,
-    scene::cornell::{ROOM, SHORT_BLOCK, TALL_BLOCK, scale_triangle},
-    shaders::PixelShaderImpl,
+    error::{Error, Result},
+    geometry::{
+        culling::{CullMode, Winding},
+        mesh::Mesh,
+        primitives::Polygon2D,
+    },
+    light::Lighting,
+    painter::{EdgeFunctionFiller, MultisampleFiller, PointPainter, PolygonFiller},
+    pixels::{ColorBuffer, PixelBuffer, PixelFormat},
+    resample::{DownsampleFilter, downsample},
+    scene::cornell::{ROOM, SHORT_BLOCK, TALL_BLOCK, planar_uvs, scale_triangle},
+    shaders::{
+        FragmentShader, InterpolationMode, Material, MultisamplePixelShader, PixelShaderImpl,
+        ShadingMode, VertexShader,
+    },
+    shadow::{ShadowMaps, ShadowSettings},
+    span::SimdLevel,
+    texture::Texture,
 };
 
+/// Polygon filling algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rasterizer {
+    /// Walks polygon edges and fills the spans between them.
+    Scanline,
+    /// Tests pixel centers against edge functions with a top-left fill
+    /// rule, shared edges are covered exactly once.
+    EdgeFunction,
+}
+
+impl TryFrom<u32> for Rasterizer {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self> {
+        Ok(match value {
+            0 => Rasterizer::Scanline,
+            1 => Rasterizer::EdgeFunction,
+            _ => {
+                return Err(Error::invalid_argument(format!(
+                    "unknown rasterizer {value}"
+                )));
+            }
+        })
+    }
+}
+
+/// Multisample anti-aliasing level.
+///
+/// The discriminants are the sample counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msaa {
+    Off = 1,
+    X2 = 2,
+    X4 = 4,
+    X8 = 8,
+}
+
+impl TryFrom<u32> for Msaa {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self> {
+        Ok(match value {
+            0 | 1 => Msaa::Off,
+            2 => Msaa::X2,
+            4 => Msaa::X4,
+            8 => Msaa::X8,
+            _ => {
+                return Err(Error::invalid_argument(format!(
+                    "unsupported MSAA sample count {value}, expected 1, 2, 4 or 8"
+                )));
+            }
+        })
+    }
+}
+
+impl Msaa {
+    pub fn sample_count(self) -> usize {
+        self as usize
+    }
+}
+
+/// Largest supported supersampling factor per axis.
+pub const MAX_SUPERSAMPLING_FACTOR: u32 = 8;
+
+/// Largest supported number of render threads.
+pub const MAX_THREADS: u32 = 256;
+
+/// Side of the square screen tiles shared out between render threads.
+pub const TILE_SIZE: u32 = 64;
+
+/// A projected polygon, the index of its mesh and the mesh texture.
+type MeshPolygon<'w, V> = (Polygon2D<V>, usize, Option<&'w Arc<Texture>>);
+
 pub struct World {
     camera: Camera,
-    triangles: Vec<Triangle>,
+    meshes: Vec<Mesh>,
+    /// Shared with the render threads, see [`World::texture_mut`].
+    textures: Vec<Arc<Texture>>,
+    pixel_format: PixelFormat,
+    cull_mode: CullMode,
+    front_face: Winding,
+    interpolation: InterpolationMode,
+    shading: ShadingMode,
+    lighting: Lighting,
+    /// Off by default, shadows are only drawn with Phong shading.
+    shadows: Option<ShadowSettings>,
+    rasterizer: Rasterizer,
+    msaa: Msaa,
+    /// Per axis render scale and the filter bringing it back to the canvas,
+    /// a factor of 1 renders directly.
+    supersampling: (u32, DownsampleFilter),
+    /// Number of render threads, 0 picks one per available core.
+    threads: u32,
+    /// Instruction set of the span shading kernels.
+    simd: SimdLevel,
+    fragment_callback: Option<FragmentCallback>,
 }
 
 impl World {
-    pub fn new(height: u32, width: u32) -> Self {
-        let triangles = [ROOM, SHORT_BLOCK, TALL_BLOCK]
+    pub fn new(height: u32, width: u32, pixel_format: PixelFormat) -> Self {
+        let meshes = [ROOM, SHORT_BLOCK, TALL_BLOCK]
             .into_iter()
-            .flatten()
-            .map(scale_triangle)
+            .map(|triangles| {
+                Mesh::new(
+                    triangles
+                        .into_iter()
+                        .map(|t| scale_triangle(planar_uvs(t)))
+                        .collect(),
+                )
+            })
             .collect();
 
         Self {
             camera: Camera::new(height, width),
-            triangles,
+            meshes,
+            textures: Vec::new(),
+            pixel_format,
+            // Cornell box faces are wound clockwise when seen from their lit side.
+            cull_mode: CullMode::Back,
+            front_face: Winding::Clockwise,
+            interpolation: InterpolationMode::Perspective,
+            shading: ShadingMode::Gouraud,
+            lighting: Lighting::default(),
+            shadows: None,
+            rasterizer: Rasterizer::Scanline,
+            msaa: Msaa::Off,
+            supersampling: (1, DownsampleFilter::Box),
+            threads: 1,
+            simd: SimdLevel::Scalar,
+            fragment_callback: None,
         }
     }
 
-    pub fn draw(&self, mut writer: PixelBuffer) {
+    pub fn draw(&self, writer: PixelBuffer) {
+        let shadows = Arc::new(match (self.shading, self.shadows) {
+            (ShadingMode::Phong, Some(settings)) => {
+                ShadowMaps::render(&self.lighting, &self.meshes, settings)
+            }
+            _ => ShadowMaps::default(),
+        });
+        let vs = self
+            .camera
+            .as_vertex_shader()
+            .with_shading(self.shading)
+            .with_lighting(self.lighting.clone());
+        let material = Material::default()
+            .with_shading(self.shading)
+            .with_lighting(self.lighting.clone())
+            .with_shadows(shadows)
+            .with_callback(self.fragment_callback);
+        self.draw_with(writer, &vs, &material);
+    }
+
+    /// Draws the meshes through custom shaders instead of the built-in
+    /// lighting, `vs` being responsible for the camera transform.
+    ///
+    /// Every render thread shades with its own clone of `fs`.
+    pub fn draw_with<VS, FS>(&self, mut writer: PixelBuffer, vs: &VS, fs: &FS)
+    where
+        VS: VertexShader<Varyings = FS::Varyings> + Sync,
+        FS: FragmentShader + Clone + Send + Sync,
+    {
         writer.memset(0);
-        let mut ps =
-            PixelShaderImpl::from_point_painter(&mut writer, self.camera.height, self.camera.width);
-        for triangle2d in self
-            .triangles
+        match self.supersampling {
+            (1, _) => self.render(&self.camera, &mut writer, vs, fs),
+            (factor, filter) => {
+                let camera = self.camera.scaled(factor);
+                let mut supersampled = ColorBuffer::new(camera.width, camera.height);
+                self.render(&camera, &mut supersampled, vs, fs);
+                downsample(&supersampled, factor, filter, &mut writer);
+            }
+        }
+    }
+
+    fn render<VS, FS>(&self, camera: &Camera, painter: &mut impl PointPainter, vs: &VS, fs: &FS)
+    where
+        VS: VertexShader<Varyings = FS::Varyings> + Sync,
+        FS: FragmentShader + Clone + Send + Sync,
+    {
+        let polygons = self.project(camera, vs);
+        let samples = self.msaa.sample_count();
+        let threads = self.thread_count();
+        if threads == 1 {
+            let mut ps =
+                PixelShaderImpl::multisampled(painter, camera.height, camera.width, samples)
+                    .with_fragment_shader(fs.clone())
+                    .with_simd_level(self.simd);
+            for (polygon, mesh, texture) in &polygons {
+                ps.set_mesh(*mesh, *texture);
+                fill(&mut ps, polygon, self.msaa, self.rasterizer);
+            }
+            ps.resolve();
+            return;
+        }
+
+        // Bin every polygon into the tiles its bounding box overlaps, the
+        // tiles are then independent and claimed by whichever thread is free.
+        let tiles = IVec2::new(
+            camera.width.div_ceil(TILE_SIZE) as i32,
+            camera.height.div_ceil(TILE_SIZE) as i32,
+        );
+        let mut bins = vec![Vec::new(); (tiles.x * tiles.y) as usize];
+        for (i, (polygon, _, _)) in polygons.iter().enumerate() {
+            let Some((min, max)) = polygon.pixel_bounds() else {
+                continue;
+            };
+            let first = (min / TILE_SIZE as i32).max(IVec2::ZERO);
+            let last = (max / TILE_SIZE as i32).min(tiles - 1);
+            for y in first.y..=last.y {
+                for x in first.x..=last.x {
+                    bins[(y * tiles.x + x) as usize].push(i);
+                }
+            }
+        }
+
+        let next_tile = AtomicUsize::new(0);
+        let (msaa, rasterizer, simd) = (self.msaa, self.rasterizer, self.simd);
+        let render_tiles = || {
+            let mut rendered = Vec::new();
+            loop {
+                let tile = next_tile.fetch_add(1, Ordering::Relaxed);
+                let Some(bin) = bins.get(tile) else {
+                    break;
+                };
+                if bin.is_empty() {
+                    continue;
+                }
+                let origin =
+                    IVec2::new(tile as i32 % tiles.x, tile as i32 / tiles.x) * TILE_SIZE as i32;
+                let width = TILE_SIZE.min(camera.width - origin.x as u32);
+                let height = TILE_SIZE.min(camera.height - origin.y as u32);
+                let mut buffer = ColorBuffer::new(width, height);
+                let mut ps = PixelShaderImpl::for_tile(&mut buffer, origin, height, width, samples)
+                    .with_fragment_shader(fs.clone())
+                    .with_simd_level(simd);
+                for &i in bin {
+                    let (polygon, mesh, texture) = &polygons[i];
+                    ps.set_mesh(*mesh, *texture);
+                    fill(&mut ps, polygon, msaa, rasterizer);
+                }
+                ps.resolve();
+                rendered.push((origin, buffer));
+            }
+            rendered
+        };
+        let rendered: Vec<_> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads).map(|_| scope.spawn(render_tiles)).collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect()
+        });
+
+        for (origin, buffer) in rendered {
+            for y in 0..buffer.height {
+                for x in 0..buffer.width {
+                    let i = buffer.index(x, y);
+                    if buffer.drawn[i] {
+                        painter.draw_point(
+                            origin.x as u32 + x,
+                            origin.y as u32 + y,
+                            &buffer.colors[i],
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Projects and culls every mesh triangle, pairing the polygons with
+    /// their mesh.
+    fn project<VS: VertexShader>(
+        &self,
+        camera: &Camera,
+        vs: &VS,
+    ) -> Vec<MeshPolygon<'_, VS::Varyings>> {
+        let viewport = camera.viewport();
+        self.meshes
             .iter()
-            .map(|t| t.project_to_canvas(&self.camera))
-        {
-            ps.fill_polygon(triangle2d);
+            .enumerate()
+            .flat_map(|(index, mesh)| {
+                let cull_mode = mesh.cull_mode.unwrap_or(self.cull_mode);
+                let texture = mesh.texture.and_then(|i| self.textures.get(i));
+                mesh.triangles
+                    .iter()
+                    .map(move |t| t.project_to_canvas(vs, viewport, self.interpolation))
+                    .filter(move |polygon| !cull_mode.culls(polygon, self.front_face))
+                    .map(move |polygon| (polygon, index, texture))
+            })
+            .collect()
+    }
+
+    /// Render threads to use, resolving 0 to the available parallelism.
+    fn thread_count(&self) -> usize {
+        match self.threads {
+            0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            threads => threads as usize,
         }
     }
 
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    pub fn lighting(&self) -> &Lighting {
+        &self.lighting
+    }
+
+    pub fn lighting_mut(&mut self) -> &mut Lighting {
+        &mut self.lighting
+    }
+
+    pub fn meshes(&self) -> &[Mesh] {
+        &self.meshes
+    }
+
+    pub fn mesh_mut(&mut self, index: usize) -> Option<&mut Mesh> {
+        self.meshes.get_mut(index)
+    }
+
+    /// Adds `mesh` and returns its index.
+    pub fn add_mesh(&mut self, mesh: Mesh) -> usize {
+        self.meshes.push(mesh);
+        self.meshes.len() - 1
+    }
+
+    /// Removes every mesh, the Cornell box included.
+    pub fn clear_meshes(&mut self) {
+        self.meshes.clear();
+    }
+
+    pub fn textures(&self) -> &[Arc<Texture>] {
+        &self.textures
+    }
+
+    /// Copies the texture first if a render still shares it.
+    pub fn texture_mut(&mut self, index: usize) -> Option<&mut Texture> {
+        self.textures.get_mut(index).map(Arc::make_mut)
+    }
+
+    /// Adds `texture` and returns its index.
+    pub fn add_texture(&mut self, texture: Texture) -> usize {
+        self.textures.push(Arc::new(texture));
+        self.textures.len() - 1
+    }
+
+    pub fn set_cull_mode(&mut self, cull_mode: CullMode) {
+        self.cull_mode = cull_mode;
+    }
+
+    pub fn set_front_face(&mut self, front_face: Winding) {
+        self.front_face = front_face;
+    }
+
+    pub fn set_interpolation_mode(&mut self, interpolation: InterpolationMode) {
+        self.interpolation = interpolation;
+    }
+
+    /// Shadows are only drawn with [`ShadingMode::Phong`], not with the
+    /// default Gouraud shading.
+    pub fn set_shading_mode(&mut self, shading: ShadingMode) {
+        self.shading = shading;
+    }
+
+    /// Worlds start without shadows, rendering shadow maps every frame
+    /// is opt-in.
+    pub fn set_shadows(&mut self, shadows: Option<ShadowSettings>) {
+        self.shadows = shadows;
+    }
+
+    pub fn set_rasterizer(&mut self, rasterizer: Rasterizer) {
+        self.rasterizer = rasterizer;
+    }
+
+    pub fn set_msaa(&mut self, msaa: Msaa) {
+        self.msaa = msaa;
+    }
+
+    pub fn set_supersampling(&mut self, factor: u32, filter: DownsampleFilter) -> Result<()> {
+        if !(1..=MAX_SUPERSAMPLING_FACTOR).contains(&factor) {
+            return Err(Error::invalid_argument(format!(
+                "supersampling factor must be within 1..={MAX_SUPERSAMPLING_FACTOR}, got {factor}"
+            )));
+        }
+        self.supersampling = (factor, filter);
+        Ok(())
+    }
+
+    pub fn set_thread_count(&mut self, threads: u32) -> Result<()> {
+        if threads > MAX_THREADS {
+            return Err(Error::invalid_argument(format!(
+                "thread count must be at most {MAX_THREADS}, got {threads}"
+            )));
+        }
+        self.threads = threads;
+        Ok(())
+    }
+
+    /// Instruction set used to depth test and interpolate spans. Only
+    /// changes performance, every level draws the same. Scalar by default,
+    /// the vector levels not reliably paying off over whole frames yet, see
+    /// `benches/span.rs`.
+    pub fn set_simd_level(&mut self, level: SimdLevel) -> Result<()> {
+        if !SimdLevel::supported().contains(&level) {
+            return Err(Error::invalid_argument(format!(
+                "{level:?} is not supported by this CPU"
+            )));
+        }
+        self.simd = level;
+        Ok(())
+    }
+
+    /// Lets `callback` recolor the fragments of [`World::draw`], see
+    /// [`crate::callback`] for when it is called.
+    pub fn set_fragment_callback(&mut self, callback: Option<FragmentCallback>) {
+        self.fragment_callback = callback;
+    }
+
     pub fn set_yaw(&mut self, yaw: f32) {
         self.camera.set_yaw(yaw);
     }
@@ -46,4 +465,200 @@ impl World {
     pub fn get_canvas_size(&self) -> (u32, u32) {
         (self.camera.height, self.camera.width)
     }
+
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.pixel_format
+    }
+}
+
+fn fill<PS: MultisamplePixelShader>(
+    ps: &mut PS,
+    polygon: &Polygon2D<PS::Varyings>,
+    msaa: Msaa,
+    rasterizer: Rasterizer,
+) {
+    // Multisampling always goes through edge functions.
+    match (msaa, rasterizer) {
+        (Msaa::Off, Rasterizer::Scanline) => ps.fill_polygon(polygon),
+        (Msaa::Off, Rasterizer::EdgeFunction) => ps.fill_polygon_edge_function(polygon),
+        _ => ps.fill_polygon_multisample(polygon),
+    }
+}
+
+/// Floats drawn by `draw_to` on an RGBA32F canvas the size of `world`'s.
+#[cfg(test)]
+pub(crate) fn render_rgba32f(world: &World, draw_to: impl FnOnce(PixelBuffer)) -> Vec<f32> {
+    let (height, width) = world.get_canvas_size();
+    let pitch = width as usize * PixelFormat::Rgba32F.bytes_per_pixel();
+    let mut buf = vec![0u8; pitch * height as usize];
+    draw_to(PixelBuffer::new(width, height, pitch, PixelFormat::Rgba32F, &mut buf).unwrap());
+    buf.chunks_exact(4)
+        .map(|c| f32::from_ne_bytes(c.try_into().unwrap()))
+        .collect()
+}
+
+/// Floats drawn by [`World::draw`], see [`render_rgba32f`].
+#[cfg(test)]
+pub(crate) fn draw_rgba32f(world: &World) -> Vec<f32> {
+    render_rgba32f(world, |buf| world.draw(buf))
+}
+
+#[cfg(test)]
+mod test {
+    use glam::{Mat4, Vec3, vec3};
+
+    use super::{Msaa, Rasterizer, World, draw_rgba32f, render_rgba32f};
+    use crate::{
+        geometry::{
+            culling::CullMode,
+            mesh::Mesh,
+            primitives::{ClipVertex, Triangle, Vertex},
+        },
+        pixels::PixelFormat,
+        shaders::{Fragment, FragmentShader, ShadingMode, VertexShader},
+        shadow::ShadowSettings,
+    };
+
+    #[test]
+    fn tiled_rendering_matches_single_thread() {
+        // Neither side is a multiple of the tile size.
+        let mut world = World::new(100, 150, PixelFormat::Rgba32F);
+        for (rasterizer, msaa) in [
+            (Rasterizer::Scanline, Msaa::Off),
+            (Rasterizer::EdgeFunction, Msaa::Off),
+            (Rasterizer::EdgeFunction, Msaa::X4),
+        ] {
+            world.set_rasterizer(rasterizer);
+            world.set_msaa(msaa);
+            world.set_thread_count(1).unwrap();
+            let single = draw_rgba32f(&world);
+            world.set_thread_count(3).unwrap();
+            // Spans cut at tile borders are interpolated from different
+            // ends, which only moves rounding.
+            let tiled = draw_rgba32f(&world);
+            assert!(
+                single.iter().zip(&tiled).all(|(a, b)| (a - b).abs() < 1e-4),
+                "{rasterizer:?} with {msaa:?} differs"
+            );
+        }
+    }
+
+    #[test]
+    fn phong_keeps_the_hotspot_inside_large_polygons() {
+        let mut world = World::new(100, 100, PixelFormat::Rgba32F);
+        // The ceiling point right above the light, lit far more than the
+        // corners of the two ceiling triangles.
+        let ndc = world
+            .camera()
+            .view_projection_matrix()
+            .project_point3(Vec3::new(0f32, -1f32, -0.7));
+        let pixel = ((ndc.truncate() * 0.5 + 0.5) * 100f32).as_uvec2();
+        let red_at = |world: &World| draw_rgba32f(world)[((pixel.y * 100 + pixel.x) * 4) as usize];
+
+        let gouraud = red_at(&world);
+        world.set_shading_mode(ShadingMode::Phong);
+        let phong = red_at(&world);
+        assert!(phong > 2f32 * gouraud, "phong {phong}, gouraud {gouraud}");
+    }
+
+    #[test]
+    fn shadows_are_opt_in() {
+        let mut world = World::new(100, 100, PixelFormat::Rgba32F);
+        world.set_shading_mode(ShadingMode::Phong);
+        let default = draw_rgba32f(&world);
+        world.set_shadows(Some(ShadowSettings::default()));
+        assert_ne!(default, draw_rgba32f(&world));
+        world.set_shadows(None);
+        assert_eq!(default, draw_rgba32f(&world));
+    }
+
+    #[test]
+    fn custom_shaders_bring_their_own_varyings() {
+        /// Hands the world space normal down as the only varying.
+        struct NormalVs(Mat4);
+
+        impl VertexShader for NormalVs {
+            type Varyings = Vec3;
+
+            fn vertex_shader(&self, vertex: &Vertex) -> ClipVertex<Vec3> {
+                ClipVertex::new(self.0 * vertex.point.extend(1f32), vertex.normal)
+            }
+        }
+
+        #[derive(Clone)]
+        struct NormalFs;
+
+        impl FragmentShader for NormalFs {
+            type Varyings = Vec3;
+
+            fn fragment_shader(&self, fragment: &Fragment<Vec3>) -> Vec3 {
+                fragment.varyings * 0.5 + 0.5
+            }
+        }
+
+        let world = World::new(100, 100, PixelFormat::Rgba32F);
+        let vs = NormalVs(world.camera().view_projection_matrix());
+        let colors = render_rgba32f(&world, |buf| world.draw_with(buf, &vs, &NormalFs));
+        // A pixel of the back wall, the two last room triangles.
+        let i = (30 * 100 + 50) * 4;
+        let expected = world.meshes()[0].triangles[8].get_normal() * 0.5 + 0.5;
+        let color = Vec3::from_slice(&colors[i..i + 3]);
+        assert!(
+            (color - expected).abs().max_element() < 1e-5,
+            "{color} {expected}"
+        );
+    }
+
+    #[test]
+    fn orthographic_depth_keeps_the_nearest_triangle() {
+        /// Hands the triangle color down unlit.
+        struct ColorVs(Mat4);
+
+        impl VertexShader for ColorVs {
+            type Varyings = Vec3;
+
+            fn vertex_shader(&self, vertex: &Vertex) -> ClipVertex<Vec3> {
+                ClipVertex::new(self.0 * vertex.point.extend(1f32), vertex.reflectance)
+            }
+        }
+
+        #[derive(Clone)]
+        struct ColorFs;
+
+        impl FragmentShader for ColorFs {
+            type Varyings = Vec3;
+
+            fn fragment_shader(&self, fragment: &Fragment<Vec3>) -> Vec3 {
+                fragment.varyings
+            }
+        }
+
+        let triangle = |z: f32, color: Vec3| {
+            Triangle::new(
+                vec3(-1.5, -1.5, z),
+                vec3(1.5, -1.5, z),
+                vec3(0f32, 1.5, z),
+                color,
+            )
+        };
+        let mut world = World::new(100, 100, PixelFormat::Rgba32F);
+        world
+            .camera_mut()
+            .set_orthographic(4f32, 0.1, 10f32)
+            .unwrap();
+        world.set_cull_mode(CullMode::None);
+        let vs = ColorVs(world.camera().view_projection_matrix());
+        // Either drawing order ends up with the nearer triangle on top.
+        for near_first in [true, false] {
+            let mut triangles = vec![triangle(0f32, Vec3::X), triangle(1f32, Vec3::Y)];
+            if !near_first {
+                triangles.reverse();
+            }
+            world.clear_meshes();
+            world.add_mesh(Mesh::new(triangles));
+            let colors = render_rgba32f(&world, |buf| world.draw_with(buf, &vs, &ColorFs));
+            let i = (50 * 100 + 50) * 4;
+            assert_eq!(Vec3::X, Vec3::from_slice(&colors[i..i + 3]));
+        }
+    }
 }